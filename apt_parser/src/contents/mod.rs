@@ -5,48 +5,77 @@ use nom::character::{is_space, is_alphanumeric, is_digit};
 use nom::sequence::{preceded, terminated, tuple};
 use nom::branch::alt;
 use nom::multi::{many0, many_m_n, separated_list0, separated_list1};
+use nom::error::ErrorKind;
 use nom::IResult;
-
+#[cfg(feature = "gzip")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+#[cfg(feature = "xz")]
+use xz2::read::XzDecoder;
+#[cfg(feature = "zstd")]
+use zstd::stream::read::Decoder as ZstdDecoder;
+#[cfg(feature = "bzip2")]
+use bzip2::read::BzDecoder;
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::io::{Read, BufRead, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::iter::Iterator;
 
-use crate::Filter;
+use crate::{AcceptAllFilter, Filter};
 
 const PATH_SEPARATOR: &str = "/";
 const SOVER_SEPARATOR: &str = ".";
 const SONAME_SEPARATOR: &str = ".so";
 const SECTION_SEPARATOR: &str = "/";
 const LIST_SEPARATOR: &str = ",";
+const COLUMN_SEPARATOR: &str = "   ";
 const NEWLINE: &str = "\n";
 
 macro_rules! generate_iterator {
     ($name:ident, $func:ident) => {
         #[derive(Debug)]
-        pub struct $name<R, F> {
+        pub struct $name<R, F, E = AcceptAllFilter> {
             reader: BufReader<R>,
             filter: F,
+            entry_filter: E,
+            skip_header: bool,
+            bytes_read: u64,
+            config: ParserConfig,
         }
 
-        impl<R: Read, F: Filter> Iterator for $name<R, F> {
+        impl<R: Read, F: Filter, E: EntryFilter> Iterator for $name<R, F, E> {
             type Item = ContentsEntry;
         
             fn next(&mut self) -> Option<Self::Item> {
+                if self.skip_header {
+                    self.skip_header = false;
+                    skip_header(&mut self.reader, &mut self.bytes_read)?;
+                }
                 let mut buf = Vec::new();
                 loop {
-                    if self.reader.read_until(b'\n', &mut buf).is_err() {
-                        return None;
+                    match self.reader.read_until(b'\n', &mut buf) {
+                        Ok(read) => self.bytes_read += read as u64,
+                        Err(_) => return None,
                     }
                     if buf.is_empty() {
                         return None;
                     }
-                    if ! self.filter.filter_bytes(&buf) {
+                    let blank = buf.iter().all(u8::is_ascii_whitespace);
+                    if (blank && ! self.filter.accept_empty()) || ! self.filter.filter_bytes(&buf) {
                         buf.clear();
                         continue;
                     }
-                    if let Ok((_, Some(entry))) = $func(&buf) {
-                        return Some(entry);
+                    if let Ok((_, Some(entry))) = $func(&self.config, trim_line_ending(&buf)) {
+                        if self.entry_filter.accept(&entry) {
+                            return Some(entry);
+                        }
                     }
                     // print!("Failed to parse: {}", String::from_utf8_lossy(&buf).to_string());
                     buf.clear();
@@ -56,17 +85,280 @@ macro_rules! generate_iterator {
 
         impl<R: Read, F: Filter> $name<R, F> {
             pub fn new(read: R, filter: F) -> Self {
+                Self::with_entry_filter(read, filter, AcceptAllFilter::new())
+            }
+        }
+
+        #[cfg(feature = "gzip")]
+        impl<R: Read, F: Filter> $name<GzDecoder<R>, F> {
+            /// Create an iterator over a gzip-compressed Contents file
+            pub fn new_gzip(read: R, filter: F) -> Self {
+                Self::new(GzDecoder::new(read), filter)
+            }
+        }
+
+        #[cfg(feature = "xz")]
+        impl<R: Read, F: Filter> $name<XzDecoder<R>, F> {
+            /// Create an iterator over a xz-compressed Contents file
+            pub fn new_xz(read: R, filter: F) -> Self {
+                Self::new(XzDecoder::new(read), filter)
+            }
+        }
+
+        #[cfg(feature = "zstd")]
+        impl<R: Read, F: Filter> $name<ZstdDecoder<'static, BufReader<R>>, F> {
+            /// Create an iterator over a zstd-compressed Contents file
+            pub fn new_zstd(read: R, filter: F) -> std::io::Result<Self> {
+                Ok(Self::new(ZstdDecoder::new(read)?, filter))
+            }
+        }
+
+        #[cfg(feature = "bzip2")]
+        impl<R: Read, F: Filter> $name<BzDecoder<R>, F> {
+            /// Create an iterator over a bzip2-compressed Contents file
+            pub fn new_bzip2(read: R, filter: F) -> Self {
+                Self::new(BzDecoder::new(read), filter)
+            }
+        }
+
+        impl<R: Read, F: Filter, E: EntryFilter> $name<R, F, E> {
+            pub fn with_entry_filter(read: R, filter: F, entry_filter: E) -> Self {
                 Self {
                     reader: BufReader::new(read),
                     filter,
+                    entry_filter,
+                    skip_header: false,
+                    bytes_read: 0,
+                    config: ParserConfig::default(),
                 }
             }
+
+            /// Recognize shared libraries with `config` instead of the ELF
+            /// conventions
+            pub fn with_config(mut self, config: ParserConfig) -> Self {
+                self.config = config;
+                self
+            }
+
+            /// Number of bytes read from the underlying reader so far
+            pub fn bytes_read(&self) -> u64 {
+                self.bytes_read
+            }
+
+            /// Skip the human-readable header preceding the entries
+            ///
+            /// Every line up to the first blank line is dropped, followed by
+            /// the `FILE   LOCATION` column titles if present. Disabled by
+            /// default, a file without a blank line yields nothing.
+            pub fn skip_header(mut self, skip_header: bool) -> Self {
+                self.skip_header = skip_header;
+                self
+            }
+
+            /// Stop after `n` entries
+            ///
+            /// Same as [`Iterator::take`], lines after the `n`th entry are
+            /// never read.
+            pub fn take_entries(self, n: usize) -> std::iter::Take<Self> {
+                self.take(n)
+            }
+
+            /// Flatten the iterator into one `(path, package)` pair per package
+            ///
+            /// See [`ContentsEntry::flatten`] for the cloning cost.
+            pub fn flat_entries(self) -> impl Iterator<Item = (ContentsPath, PackageName)> {
+                self.flat_map(ContentsEntry::flatten)
+            }
         }
     };
 }
 
+/// Consume the header of a Contents file, see `skip_header` on the iterators
+fn skip_header<R: Read>(reader: &mut BufReader<R>, bytes_read: &mut u64) -> Option<()> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf).ok()?;
+        *bytes_read += read as u64;
+        if read == 0 {
+            return None;
+        }
+        if buf.iter().all(|c| c.is_ascii_whitespace()) {
+            break;
+        }
+    }
+    let titles = reader.fill_buf().ok()?;
+    let end = titles.iter().position(|c| *c == b'\n').map_or(titles.len(), |i| i + 1);
+    let mut columns = titles[..end].split(|c| c.is_ascii_whitespace()).filter(|column| !column.is_empty());
+    if columns.next() == Some(b"FILE") && columns.next() == Some(b"LOCATION") && columns.next().is_none() {
+        reader.consume(end);
+        *bytes_read += end as u64;
+    }
+    Some(())
+}
+
+/// Iterator over a Contents file reporting lines which failed to parse
+///
+/// Lines rejected by the filter are skipped silently, and so are blank lines
+/// unless [`Filter::accept_empty`] lets them through. Every other line yields
+/// either the parsed entry or a [`ContentsParseError`]. A read error, e.g. a
+/// truncated gzip stream, is reported as [`ContentsError::Io`] and ends the
+/// iteration.
+#[derive(Debug)]
+pub struct ContentsResultIterator<R, F> {
+    reader: BufReader<R>,
+    filter: F,
+    failed: bool,
+    config: ParserConfig,
+}
+
+/// Iterator over a Contents file yielding each entry with its raw line
+///
+/// Meant for debugging the parser: every yielded line is kept in memory,
+/// trailing newline included, so prefer [`ContentsIterator`] otherwise.
+#[derive(Debug)]
+pub struct ContentsRawIterator<R, F> {
+    reader: BufReader<R>,
+    filter: F,
+    config: ParserConfig,
+}
+
+/// Reason a line failed to parse, as returned by [`parse_line`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentsError {
+    /// Line ending before the package column
+    UnexpectedEof,
+    /// Path column which is not a file path, e.g. `usr/lib/`
+    MalformedPath,
+    /// Package column which is not a list of packages
+    MalformedPackage,
+    /// Line longer than the bound given to [`take_line_bounded`]
+    TooLarge,
+    /// Error reading the input, the line holds what was read before it
+    Io(std::io::ErrorKind),
+    /// Any other parser error, e.g. [`ErrorKind::Eof`] for data left after
+    /// the package list
+    Nom(ErrorKind),
+}
+
+/// Error for a line inside a Contents file which could not be parsed
+///
+/// Pairs the [`ContentsError`] with the offending line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentsParseError {
+    line: Vec<u8>,
+    error: ContentsError,
+}
+
+impl<R: Read, F: Filter> Iterator for ContentsResultIterator<R, F> {
+    type Item = Result<ContentsEntry, ContentsParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let mut buf = Vec::new();
+        loop {
+            if let Err(e) = self.reader.read_until(b'\n', &mut buf) {
+                self.failed = true;
+                return Some(Err(ContentsParseError::new(buf, ContentsError::Io(e.kind()))));
+            }
+            if buf.is_empty() {
+                return None;
+            }
+            let blank = buf.iter().all(u8::is_ascii_whitespace);
+            if (blank && ! self.filter.accept_empty()) || ! self.filter.filter_bytes(&buf) {
+                buf.clear();
+                continue;
+            }
+            return Some(parse_line_with(&self.config, &buf).map_err(|error| ContentsParseError::new(buf, error)));
+        }
+    }
+}
+
+impl<R: Read, F: Filter> ContentsResultIterator<R, F> {
+    pub fn new(read: R, filter: F) -> Self {
+        Self {
+            reader: BufReader::new(read),
+            filter,
+            failed: false,
+            config: ParserConfig::default(),
+        }
+    }
+
+    /// Recognize shared libraries with `config` instead of the ELF
+    /// conventions
+    pub fn with_config(mut self, config: ParserConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl<R: Read, F: Filter> Iterator for ContentsRawIterator<R, F> {
+    type Item = (ContentsEntry, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = Vec::new();
+        loop {
+            if self.reader.read_until(b'\n', &mut buf).is_err() {
+                return None;
+            }
+            if buf.is_empty() {
+                return None;
+            }
+            let blank = buf.iter().all(u8::is_ascii_whitespace);
+            if (blank && ! self.filter.accept_empty()) || ! self.filter.filter_bytes(&buf) {
+                buf.clear();
+                continue;
+            }
+            if let Ok((_, Some(entry))) = take_line_with(&self.config, trim_line_ending(&buf)) {
+                return Some((entry, buf));
+            }
+            buf.clear();
+        }
+    }
+}
+
+impl<R: Read, F: Filter> ContentsRawIterator<R, F> {
+    pub fn new(read: R, filter: F) -> Self {
+        Self {
+            reader: BufReader::new(read),
+            filter,
+            config: ParserConfig::default(),
+        }
+    }
+
+    /// Recognize shared libraries with `config` instead of the ELF
+    /// conventions
+    pub fn with_config(mut self, config: ParserConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+/// Filter applied to successfully parsed entries
+///
+/// Unlike [`Filter`], which only sees the raw line, an entry filter can
+/// inspect the structured result before the iterator yields it.
+pub trait EntryFilter {
+    fn accept(&self, entry: &ContentsEntry) -> bool;
+}
+
+impl EntryFilter for AcceptAllFilter {
+    fn accept(&self, _entry: &ContentsEntry) -> bool {
+        true
+    }
+}
+
+impl<T: Fn(&ContentsEntry) -> bool> EntryFilter for T {
+    fn accept(&self, entry: &ContentsEntry) -> bool {
+        self(entry)
+    }
+}
+
 /// Shared Library
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SharedLibrary {
     name: String,
     sover: Vec<usize>,
@@ -76,13 +368,18 @@ pub struct SharedLibrary {
 /// 
 /// A file path could either be a shared library or a normal file
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum File {
     SharedLibrary(SharedLibrary),
     Normal(String),
 }
 
 /// Path inside a Contents file
+///
+/// Files at the archive root, e.g. `foo   sect/pkg`, have an empty parent
+/// rather than `.`, so the path displays back as `foo`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ContentsPath {
     parent: PathBuf,
     file: File,
@@ -90,6 +387,7 @@ pub struct ContentsPath {
 
 /// Name of a package
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PackageName {
     area: Option<String>,
     section: Option<String>,
@@ -98,27 +396,79 @@ pub struct PackageName {
 
 /// Entry inside a Contents file
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ContentsEntry {
     path: ContentsPath,
     packages: Vec<PackageName>,
 }
 
-generate_iterator!(ContentsIterator, take_line);
-generate_iterator!(ContentsSharedLibraryIterator, take_line_so);
+/// In-memory index answering which packages ship a path
+///
+/// Paths are keyed without their leading `./`, so `./usr/bin/bash` and
+/// `usr/bin/bash` are the same key.
+#[derive(Clone, Debug, Default)]
+pub struct ContentsIndex {
+    entries: HashMap<String, ContentsEntry>,
+}
+
+/// Counts gathered over the entries of a Contents file, see [`stats`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ContentsStats {
+    entries: usize,
+    shared_libraries: usize,
+    packages: usize,
+}
+
+/// Naming conventions used to recognize shared libraries
+///
+/// Defaults to [`ParserConfig::ELF`], e.g. `libnuma.so.1.1.4`. The
+/// [`fmt::Display`] impls always write ELF names, use the `to_string_with`
+/// methods to print entries parsed with another config back as they were.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParserConfig {
+    soname_separator: Cow<'static, str>,
+    sover_separator: Cow<'static, str>,
+    sover_position: SoverPosition,
+}
+
+/// Where the sover components sit relative to the soname separator
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SoverPosition {
+    /// After the separator, e.g. `libnuma.so.1.1.4`
+    #[default]
+    After,
+    /// Before the separator, e.g. `libfoo.1.dylib`
+    Before,
+}
+
+generate_iterator!(ContentsIterator, take_line_with);
+generate_iterator!(ContentsSharedLibraryIterator, take_line_so_with);
+
+/// Strip a trailing `\n`, `\r\n` or `\r` off a line
+#[inline]
+pub fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(NEWLINE.as_bytes()).unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
 
 #[inline]
 fn separator(input: &[u8]) -> IResult<&[u8], &[u8]> {
     take_while(is_space)(input)
 }
 
+/// Area and section names: ASCII letters of either case, digits and `-`
 #[inline]
 fn is_section_name(chr: u8) -> bool {
-    is_alphanumeric(chr) || [b'-'].contains(&chr)
+    chr.is_ascii_lowercase() || chr.is_ascii_uppercase() || chr.is_ascii_digit() || chr == b'-'
 }
 
+/// Package names, per Debian policy: lowercase letters, digits and `+-_.`
 #[inline]
 fn is_package_name(chr: u8) -> bool {
-    (b'a'..=b'z').contains(&chr) || (b'0'..=b'9').contains(&chr) || [b'+', b'-', b'_', b'.'].contains(&chr)
+    chr.is_ascii_lowercase() || chr.is_ascii_digit() || [b'+', b'-', b'_', b'.'].contains(&chr)
 }
 
 #[inline]
@@ -126,7 +476,6 @@ fn is_soname(chr: u8) -> bool {
     is_alphanumeric(chr) || [b'+', b'-', b'_'].contains(&chr)
 }
 
-// TODO: Accept filenames with spaces
 #[inline]
 fn is_file_name(chr: u8) -> bool {
     //is_alphanumeric(chr) || [b'+', b'-', b':', b'.', b'_', b'!', b'$', b'(', b')', b'@', b'~', b'{', b'}', b'#', b',', b'\'', b'%'].contains(&chr)
@@ -141,29 +490,60 @@ fn take_path_segment(input: &[u8]) -> IResult<&[u8], &[u8]> {
 #[inline]
 fn many0_path_segments(input: &[u8]) -> IResult<&[u8], PathBuf> {
     let (i, segments) = many0(take_path_segment)(input)?;
+    if segments.is_empty() {
+        return Ok((i, PathBuf::new()));
+    }
     let path = String::from_utf8_lossy(&segments.join(&b'/')).to_string();
     Ok((i, PathBuf::from(path)))
 }
 
 #[inline]
-fn sover_segment(input: &[u8]) -> IResult<&[u8], usize> {
-    let (i, sover) = preceded(tag(SOVER_SEPARATOR), take_while1(is_digit))(input)?;
+fn sover_segment<'a>(config: &ParserConfig, input: &'a [u8]) -> IResult<&'a [u8], usize> {
+    let (i, sover) = preceded(tag(config.get_sover_separator()), take_while1(is_digit))(input)?;
     Ok((i, sover.iter().fold(0, |acc, digit| {
         acc * 10 + (digit - b'0') as usize
     })))
 }
 
 #[inline]
+fn many0_sover_segment_with<'a>(config: &ParserConfig, input: &'a [u8]) -> IResult<&'a [u8], Vec<usize>> {
+    many0(|i| sover_segment(config, i))(input)
+}
+
+#[cfg(test)]
 fn many0_sover_segment(input: &[u8]) -> IResult<&[u8], Vec<usize>> {
-    many0(sover_segment)(input)
+    many0_sover_segment_with(&ParserConfig::ELF, input)
 }
 
+/// Parse a shared library, e.g. `libnuma.so.1.1.4`
+///
+/// Every sover component must be numeric and the name must end right after
+/// the sover, or after the soname separator for [`SoverPosition::Before`].
+/// Names such as `libfoo.so.1a` or `libfoo.so.1.2-rc` fail here as a whole,
+/// so that [`take_file`] returns them as [`File::Normal`] instead of a
+/// partially parsed library.
 #[inline]
-fn take_file_so(input: &[u8]) -> IResult<&[u8], File> {
-    let (i, (soname, sover, _)) = tuple((terminated(take_while1(is_soname), tag(SONAME_SEPARATOR)), many0_sover_segment, take_while1(is_space)))(input)?;
+fn take_file_so_with<'a>(config: &ParserConfig, input: &'a [u8]) -> IResult<&'a [u8], File> {
+    let (i, (soname, sover, _)) = match config.get_sover_position() {
+        SoverPosition::After => tuple((
+            terminated(take_while1(is_soname), tag(config.get_soname_separator())),
+            |i| many0_sover_segment_with(config, i),
+            take_while1(is_space),
+        ))(input)?,
+        SoverPosition::Before => tuple((
+            take_while1(is_soname),
+            terminated(|i| many0_sover_segment_with(config, i), tag(config.get_soname_separator())),
+            take_while1(is_space),
+        ))(input)?,
+    };
     Ok((i, File::so(soname, sover)))
 }
 
+#[cfg(test)]
+fn take_file_so(input: &[u8]) -> IResult<&[u8], File> {
+    take_file_so_with(&ParserConfig::ELF, input)
+}
+
 #[inline]
 fn take_file_else(input: &[u8]) -> IResult<&[u8], File> {
     let (i, (name, _)) = tuple((take_while(is_file_name), separator))(input)?;
@@ -171,25 +551,44 @@ fn take_file_else(input: &[u8]) -> IResult<&[u8], File> {
 }
 
 #[inline]
+fn take_file_with<'a>(config: &ParserConfig, input: &'a [u8]) -> IResult<&'a [u8], File> {
+    alt((|i| take_file_so_with(config, i), take_file_else))(input)
+}
+
+#[cfg(test)]
 fn take_file(input: &[u8]) -> IResult<&[u8], File> {
-    alt((take_file_so, take_file_else))(input)
+    take_file_with(&ParserConfig::ELF, input)
 }
 
 #[inline]
-fn take_path(input: &[u8]) -> IResult<&[u8], ContentsPath> {
-    let (i, (path, file)) = tuple((many0_path_segments, take_file))(input)?;
+fn take_path_with<'a>(config: &ParserConfig, input: &'a [u8]) -> IResult<&'a [u8], ContentsPath> {
+    let (i, (path, file)) = tuple((many0_path_segments, |i| take_file_with(config, i)))(input)?;
     Ok((i, ContentsPath::new(path, file)))
 }
 
+#[cfg(test)]
+fn take_path(input: &[u8]) -> IResult<&[u8], ContentsPath> {
+    take_path_with(&ParserConfig::ELF, input)
+}
+
 #[inline]
-fn take_path_so(input: &[u8]) -> IResult<&[u8], ContentsPath> {
-    let (i, (path, file)) = tuple((many0_path_segments, take_file_so))(input)?;
+fn take_path_so_with<'a>(config: &ParserConfig, input: &'a [u8]) -> IResult<&'a [u8], ContentsPath> {
+    let (i, (path, file)) = tuple((many0_path_segments, |i| take_file_so_with(config, i)))(input)?;
     Ok((i, ContentsPath::new(path, file)))
 }
 
+/// Package name, failing with [`ErrorKind::Verify`] on an uppercase letter
+/// instead of silently truncating the name before it
 #[inline]
 fn take_package_name(input: &[u8]) -> IResult<&[u8], &[u8]> {
-    take_while1(is_package_name)(input)
+    let (i, name) = take_while(is_package_name)(input)?;
+    if i.first().is_some_and(u8::is_ascii_uppercase) {
+        return Err(nom::Err::Failure(nom::error::Error::new(i, ErrorKind::Verify)));
+    }
+    if name.is_empty() {
+        return Err(nom::Err::Error(nom::error::Error::new(input, ErrorKind::TakeWhile1)));
+    }
+    Ok((i, name))
 }
 
 #[inline]
@@ -214,51 +613,210 @@ fn take_package(input: &[u8]) -> IResult<&[u8], PackageName> {
     Ok((i, package))
 }
 
+#[inline]
+fn take_list_separator(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    tuple((separator, tag(LIST_SEPARATOR), separator))(input).map(|(i, (_, sep, _))| (i, sep))
+}
+
+/// Package list, tolerating spaces around the commas and trailing whitespace
 #[inline]
 fn take_packages(input: &[u8]) -> IResult<&[u8], Vec<PackageName>> {
-    preceded(separator, separated_list1(tag(LIST_SEPARATOR), take_package))(input)
+    terminated(
+        preceded(separator, separated_list1(take_list_separator, take_package)),
+        separator,
+    )(input)
 }
 
+/// Find the start of the whitespace column between the path and the package list
+///
+/// File names may contain single spaces, so the column is the last run of two
+/// or more spaces, or any run containing a tab. Lines without such a run fall
+/// back to the last single space.
 #[inline]
-pub fn take_line(input: &[u8]) -> IResult<&[u8], Option<ContentsEntry>> {
-    let mut separate = input.len();
-    for i in (0..input.len()).rev() {
-        if is_space(input[i]) {
-            separate = i;
-            break;
+pub(crate) fn find_column_separator(input: &[u8]) -> Option<usize> {
+    let mut fallback = None;
+    let mut end = input
+        .iter()
+        .rposition(|c| !c.is_ascii_whitespace())
+        .map_or(0, |i| i + 1);
+    while end > 0 {
+        if !is_space(input[end - 1]) {
+            end -= 1;
+            continue;
+        }
+        let mut start = end - 1;
+        while start > 0 && is_space(input[start - 1]) {
+            start -= 1;
+        }
+        // Spaces after a comma belong to the package list
+        if start > 0 && input[start - 1] == LIST_SEPARATOR.as_bytes()[0] {
+            end = start;
+            continue;
+        }
+        let run = &input[start..end];
+        if run.len() >= 2 || run.contains(&b'\t') {
+            return Some(start);
         }
+        fallback = fallback.or(Some(start));
+        end = start;
     }
-    let (_, path) = take_path(&input[..=separate])?;
+    fallback
+}
+
+#[inline]
+pub fn take_line(input: &[u8]) -> IResult<&[u8], Option<ContentsEntry>> {
+    take_line_with(&ParserConfig::ELF, input)
+}
+
+/// Same as [`take_line`], recognizing shared libraries with `config`
+#[inline]
+pub fn take_line_with<'a>(config: &ParserConfig, input: &'a [u8]) -> IResult<&'a [u8], Option<ContentsEntry>> {
+    let separate = find_column_separator(input)
+        .ok_or_else(|| nom::Err::Error(nom::error::Error::new(input, ErrorKind::Space)))?;
+    let (_, path) = take_path_with(config, &input[..=separate])?;
     let (i, packages) = take_packages(&input[separate..])?;
     Ok((i, Some(ContentsEntry::new(path, packages))))
 }
 
 #[inline]
 pub fn take_line_so(input: &[u8]) -> IResult<&[u8], Option<ContentsEntry>> {
-    let mut separate = input.len();
-    for i in (0..input.len()).rev() {
-        if is_space(input[i]) {
-            separate = i;
-            break;
-        }
-    }
-    let (_, path) = take_path_so(&input[..=separate])?;
+    take_line_so_with(&ParserConfig::ELF, input)
+}
+
+/// Same as [`take_line_so`], recognizing shared libraries with `config`
+#[inline]
+pub fn take_line_so_with<'a>(config: &ParserConfig, input: &'a [u8]) -> IResult<&'a [u8], Option<ContentsEntry>> {
+    let separate = find_column_separator(input)
+        .ok_or_else(|| nom::Err::Error(nom::error::Error::new(input, ErrorKind::Space)))?;
+    let (_, path) = take_path_so_with(config, &input[..=separate])?;
     let (i, packages) = take_packages(&input[separate..])?;
     Ok((i, Some(ContentsEntry::new(path, packages))))
 }
 
+/// Same as [`parse_line`], keeping the line in the error
+///
+/// Every public type is owned, so the result outlives `line`.
+pub fn take_line_owned(line: &[u8]) -> Result<ContentsEntry, ContentsParseError> {
+    take_line_owned_with(&ParserConfig::ELF, line)
+}
+
+/// Same as [`take_line_owned`], recognizing shared libraries with `config`
+pub fn take_line_owned_with(config: &ParserConfig, line: &[u8]) -> Result<ContentsEntry, ContentsParseError> {
+    let line = trim_line_ending(line);
+    parse_line_with(config, line).map_err(|error| ContentsParseError::new(line.to_vec(), error))
+}
+
+/// Parse a single line, telling which column is malformed on failure
+///
+/// This is what every other single-line entry point builds on. A trailing
+/// newline is optional, anything else left after the package list is
+/// reported as [`ErrorKind::Eof`].
+pub fn parse_line(line: &[u8]) -> Result<ContentsEntry, ContentsError> {
+    parse_line_with(&ParserConfig::ELF, line)
+}
+
+/// Same as [`parse_line`], recognizing shared libraries with `config`
+pub fn parse_line_with(config: &ParserConfig, line: &[u8]) -> Result<ContentsEntry, ContentsError> {
+    let line = trim_line_ending(line);
+    let separate = find_column_separator(line).ok_or(ContentsError::UnexpectedEof)?;
+    let path = match take_path_with(config, &line[..=separate]) {
+        Ok((rest, path)) if rest.iter().all(|c| is_space(*c)) && !path.file.to_string().is_empty() => path,
+        _ => return Err(ContentsError::MalformedPath),
+    };
+    match take_packages(&line[separate..]) {
+        Ok((&[], packages)) => Ok(ContentsEntry::new(path, packages)),
+        Ok(_) => Err(ContentsError::Nom(ErrorKind::Eof)),
+        Err(_) => Err(ContentsError::MalformedPackage),
+    }
+}
+
+/// Same as [`take_line_owned`], refusing lines longer than `max_len` bytes
+///
+/// Overlong lines are rejected with [`ContentsError::TooLarge`] before any
+/// parsing happens, and only their first `max_len` bytes are kept in the
+/// error. Use this on Contents files from untrusted repositories.
+pub fn take_line_bounded(line: &[u8], max_len: usize) -> Result<ContentsEntry, ContentsParseError> {
+    take_line_bounded_with(&ParserConfig::ELF, line, max_len)
+}
+
+/// Same as [`take_line_bounded`], recognizing shared libraries with `config`
+pub fn take_line_bounded_with(config: &ParserConfig, line: &[u8], max_len: usize) -> Result<ContentsEntry, ContentsParseError> {
+    if trim_line_ending(line).len() > max_len {
+        return Err(ContentsParseError::new(line[..max_len].to_vec(), ContentsError::TooLarge));
+    }
+    take_line_owned_with(config, line)
+}
+
+/// Entry point for fuzzers such as cargo-fuzz
+///
+/// Parses `data` as a single bounded line and panics if the bound is not
+/// honoured; any other outcome is fine.
+#[cfg(feature = "fuzz")]
+pub fn fuzz_take_line(data: &[u8]) {
+    const MAX_LEN: usize = 4096;
+    match take_line_bounded(data, MAX_LEN) {
+        Err(e) if e.get_error() == ContentsError::TooLarge => assert!(trim_line_ending(data).len() > MAX_LEN),
+        Err(e) => assert!(e.get_line().len() <= data.len()),
+        Ok(_) => assert!(trim_line_ending(data).len() <= MAX_LEN),
+    }
+}
+
 #[inline]
 pub fn parse_multiple_line(input: &[u8]) -> IResult<&[u8], Vec<Option<ContentsEntry>>> {
     separated_list0(tag(NEWLINE), take_line)(input)
 }
 
+/// Collect every entry of a Contents file, see [`ContentsIterator`]
+pub fn parse_all<R: Read, F: Filter>(read: R, filter: F) -> Vec<ContentsEntry> {
+    ContentsIterator::new(read, filter).collect()
+}
+
+/// Collect every shared library entry of a Contents file, see
+/// [`ContentsSharedLibraryIterator`]
+pub fn parse_all_so<R: Read, F: Filter>(read: R, filter: F) -> Vec<ContentsEntry> {
+    ContentsSharedLibraryIterator::new(read, filter).collect()
+}
+
+/// Count the entries, shared libraries and distinct packages of `entries`
+///
+/// Entries are consumed one at a time, only the package names are kept in
+/// memory. Packages are told apart by name, regardless of their section.
+pub fn stats<I: IntoIterator<Item = ContentsEntry>>(entries: I) -> ContentsStats {
+    entries.into_iter().collect()
+}
+
+/// Parse a whole Contents file in parallel
+///
+/// The entire input is read into memory before the lines are parsed across
+/// the rayon thread pool, so peak memory grows with the size of the
+/// (decompressed) file. Use [`ContentsIterator`] to stream large files with
+/// constant memory instead. The order of the returned entries matches the
+/// order of the lines in the input.
+///
+/// Like the iterators, the filter sees each line with its trailing newline
+/// and blank lines only reach it if [`Filter::accept_empty`] says so.
+#[cfg(feature = "rayon")]
+pub fn par_parse<R: Read, F: Filter + Sync>(read: R, filter: F) -> std::io::Result<Vec<ContentsEntry>> {
+    par_parse_with(read, filter, &ParserConfig::ELF)
+}
+
+/// Same as [`par_parse`], recognizing shared libraries with `config`
+#[cfg(feature = "rayon")]
+pub fn par_parse_with<R: Read, F: Filter + Sync>(mut read: R, filter: F, config: &ParserConfig) -> std::io::Result<Vec<ContentsEntry>> {
+    let mut buf = Vec::new();
+    read.read_to_end(&mut buf)?;
+    Ok(buf.par_split_inclusive(|chr| *chr == b'\n')
+        .filter(|line| {
+            let blank = line.iter().all(u8::is_ascii_whitespace);
+            (!blank || filter.accept_empty()) && filter.filter_bytes(line)
+        })
+        .filter_map(|line| take_line_with(config, trim_line_ending(line)).ok().and_then(|(_, entry)| entry))
+        .collect())
+}
+
 impl fmt::Display for SharedLibrary {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        write!(f, "{}{}", self.name, SONAME_SEPARATOR)?;
-        for segment in &self.sover {
-            write!(f, ".{}", segment)?;
-        }
-        Ok(())
+        self.write_with(f, &ParserConfig::ELF)
     }
 }
 
@@ -273,121 +831,507 @@ impl fmt::Display for File {
 
 impl fmt::Display for ContentsPath {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        let path = self.parent.join(self.file.to_string());
-        write!(f, "{}", path.to_string_lossy())
+        write!(f, "{}", self.to_string_with(&ParserConfig::ELF))
     }
 }
 
-impl SharedLibrary {
-    pub fn from_bytes(soname: &[u8], sover: Vec<usize>) -> Self {
-        Self {
-            name: String::from_utf8_lossy(soname).trim_end().to_string(),
-            sover,
+impl fmt::Display for PackageName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        if let Some(area) = &self.area {
+            write!(f, "{}{}", area, SECTION_SEPARATOR)?;
         }
+        if let Some(section) = &self.section {
+            write!(f, "{}{}", section, SECTION_SEPARATOR)?;
+        }
+        write!(f, "{}", self.name)
     }
+}
 
-    pub fn get_name(&self) -> &str {
-        &self.name
-    }
-
-    pub fn get_sover(&self) -> &[usize] {
-        &self.sover
+impl fmt::Display for ContentsEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        self.write_with(f, &ParserConfig::ELF)
     }
 }
 
-impl File {
-    pub fn so(soname: &[u8], sover: Vec<usize>) -> Self {
-        Self::SharedLibrary(SharedLibrary::from_bytes(soname, sover))
+impl ContentsEntry {
+    /// Same as [`ToString::to_string`], writing shared libraries with `config`
+    pub fn to_string_with(&self, config: &ParserConfig) -> String {
+        let mut line = String::new();
+        // Writing into a String never fails
+        let _ = self.write_with(&mut line, config);
+        line
     }
 
-    pub fn normal(name: &[u8]) -> Self {
-        Self::Normal(String::from_utf8_lossy(name).trim_end().to_string())
+    fn write_with<W: fmt::Write>(&self, f: &mut W, config: &ParserConfig) -> fmt::Result {
+        write!(f, "{}{}", self.path.to_string_with(config), COLUMN_SEPARATOR)?;
+        for (i, package) in self.packages.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", LIST_SEPARATOR)?;
+            }
+            write!(f, "{}", package)?;
+        }
+        Ok(())
     }
 }
 
-impl ContentsPath {
-    pub fn new(parent: PathBuf, file: File) -> Self {
-        Self {
-            parent,
-            file,
-        }
+impl fmt::Display for ContentsParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "failed to parse line ({}): {}", self.error, String::from_utf8_lossy(&self.line).trim_end())
     }
 }
 
-impl PackageName {
-    pub fn from_bytes(area: Option<&[u8]>, section: Option<&[u8]>, name: &[u8]) -> Self {
-        Self {
-            area: area.map(|a| String::from_utf8_lossy(a).to_string()),
-            section: section.map(|s| String::from_utf8_lossy(s).to_string()),
-            name: String::from_utf8_lossy(name).to_string(),
+impl std::error::Error for ContentsParseError {}
+
+impl fmt::Display for ContentsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ContentsError::UnexpectedEof => write!(f, "unexpected end of line"),
+            ContentsError::MalformedPath => write!(f, "malformed path"),
+            ContentsError::MalformedPackage => write!(f, "malformed package list"),
+            ContentsError::TooLarge => write!(f, "line too long"),
+            ContentsError::Io(kind) => write!(f, "read error: {}", kind),
+            ContentsError::Nom(kind) => write!(f, "parser error: {}", kind.description()),
         }
     }
 }
 
-impl ContentsEntry {
-    pub fn new(path: ContentsPath, packages: Vec<PackageName>) -> Self {
+impl std::error::Error for ContentsError {}
+
+impl ContentsParseError {
+    pub fn new(line: Vec<u8>, error: ContentsError) -> Self {
         Self {
-            path,
-            packages,
+            line,
+            error,
         }
     }
 
-    pub fn get_path(&self) -> &ContentsPath {
-        &self.path
+    /// Raw line which failed to parse, as read from the input
+    pub fn get_line(&self) -> &[u8] {
+        &self.line
     }
 
-    pub fn get_packages(&self) -> &[PackageName] {
-        &self.packages
+    pub fn get_error(&self) -> ContentsError {
+        self.error
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::{File, ContentsEntry, SharedLibrary, ContentsPath, PackageName, ContentsIterator, ContentsSharedLibraryIterator, many0_path_segments, many0_sover_segment, take_file_so, take_file, take_path, take_line, take_package, take_packages};
-    use crate::AcceptAllFilter;
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self::ELF
+    }
+}
 
-    #[cfg(not(debug_assertions))]
-    use flate2::read::GzDecoder;
+impl ParserConfig {
+    /// Linux conventions, `.so` followed by `.`-separated sover components
+    pub const ELF: ParserConfig = ParserConfig {
+        soname_separator: Cow::Borrowed(SONAME_SEPARATOR),
+        sover_separator: Cow::Borrowed(SOVER_SEPARATOR),
+        sover_position: SoverPosition::After,
+    };
 
-    use std::fs;
-    use std::env;
-    use std::path::PathBuf;
+    /// macOS conventions, `.`-separated sover components followed by
+    /// `.dylib`, e.g. `libfoo.1.dylib`
+    pub const MACHO: ParserConfig = ParserConfig {
+        soname_separator: Cow::Borrowed(".dylib"),
+        sover_separator: Cow::Borrowed(SOVER_SEPARATOR),
+        sover_position: SoverPosition::Before,
+    };
 
-    #[test]
-    fn test_many0_path_segments() {
-        assert_eq!(many0_path_segments(b"/usr/bin/bash "), Ok((&b"bash "[..], PathBuf::from("/usr/bin"))));
-        assert_eq!(many0_path_segments(b"/usr/bin "), Ok((&b"bin "[..], PathBuf::from("/usr"))));
-        assert_eq!(many0_path_segments(b"./usr/bin "), Ok((&b"bin "[..], PathBuf::from("./usr"))));
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    #[test]
-    fn test_many0_sover_segment() {
-        assert_eq!(many0_sover_segment(b".1.1.4 "), Ok((&b" "[..], vec![1, 1, 4])));
-        assert_eq!(many0_sover_segment(b" "), Ok((&b" "[..], vec![])));
+    /// Suffix ending the library name, e.g. `.dylib`
+    pub fn with_soname_separator<S: Into<Cow<'static, str>>>(mut self, separator: S) -> Self {
+        self.soname_separator = separator.into();
+        self
     }
 
-    #[test]
-    fn test_take_file_so() {
-        assert_eq!(take_file_so(b"libnuma.so.1.1.4 "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
-            name: "libnuma".to_string(),
-            sover: vec![1, 1, 4],
-        }))));
-        assert_eq!(take_file_so(b"libnuma.so.1.1.4.5.1.4 "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
-            name: "libnuma".to_string(),
-            sover: vec![1, 1, 4, 5, 1, 4],
-        }))));
-        assert_eq!(take_file_so(b"libnuma.so "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
-            name: "libnuma".to_string(),
-            sover: vec![],
-        }))));
-        assert!(take_file_so(b"bash ").is_err());
+    /// Separator preceding each sover component
+    pub fn with_sover_separator<S: Into<Cow<'static, str>>>(mut self, separator: S) -> Self {
+        self.sover_separator = separator.into();
+        self
     }
 
-    #[test]
-    fn test_take_file() {
-        assert_eq!(take_file(b"libnuma.so.1.1.4 "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
-            name: "libnuma".to_string(),
-            sover: vec![1, 1, 4],
+    pub fn get_soname_separator(&self) -> &str {
+        &self.soname_separator
+    }
+
+    pub fn with_sover_position(mut self, position: SoverPosition) -> Self {
+        self.sover_position = position;
+        self
+    }
+
+    pub fn get_sover_separator(&self) -> &str {
+        &self.sover_separator
+    }
+
+    pub fn get_sover_position(&self) -> SoverPosition {
+        self.sover_position
+    }
+}
+
+impl SharedLibrary {
+    pub fn from_bytes(soname: &[u8], sover: Vec<usize>) -> Self {
+        Self {
+            name: String::from_utf8_lossy(soname).trim_end().to_string(),
+            sover,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_sover(&self) -> &[usize] {
+        &self.sover
+    }
+
+    /// Same as [`ToString::to_string`], following `config` instead of the
+    /// ELF conventions
+    pub fn to_string_with(&self, config: &ParserConfig) -> String {
+        let mut name = String::new();
+        // Writing into a String never fails
+        let _ = self.write_with(&mut name, config);
+        name
+    }
+
+    fn write_with<W: fmt::Write>(&self, f: &mut W, config: &ParserConfig) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if config.get_sover_position() == SoverPosition::After {
+            write!(f, "{}", config.get_soname_separator())?;
+        }
+        for segment in &self.sover {
+            write!(f, "{}{}", config.get_sover_separator(), segment)?;
+        }
+        if config.get_sover_position() == SoverPosition::Before {
+            write!(f, "{}", config.get_soname_separator())?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared libraries are ordered by name, then by sover
+///
+/// Sovers are compared numerically segment by segment, a missing trailing
+/// segment counts as 0 so `1.2` < `1.2.1`. Sovers which only differ by
+/// trailing zeros (`1.2` and `1.2.0`) fall back to the number of segments to
+/// stay consistent with [`Eq`].
+impl Ord for SharedLibrary {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name.cmp(&other.name).then_with(|| {
+            let len = self.sover.len().max(other.sover.len());
+            (0..len)
+                .map(|i| {
+                    let a = self.sover.get(i).copied().unwrap_or(0);
+                    let b = other.sover.get(i).copied().unwrap_or(0);
+                    a.cmp(&b)
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| self.sover.len().cmp(&other.sover.len()))
+        })
+    }
+}
+
+impl PartialOrd for SharedLibrary {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl File {
+    pub fn so(soname: &[u8], sover: Vec<usize>) -> Self {
+        Self::SharedLibrary(SharedLibrary::from_bytes(soname, sover))
+    }
+
+    pub fn normal(name: &[u8]) -> Self {
+        Self::Normal(String::from_utf8_lossy(name).trim_end().to_string())
+    }
+
+    pub fn is_shared_library(&self) -> bool {
+        matches!(self, Self::SharedLibrary(_))
+    }
+
+    pub fn as_shared_library(&self) -> Option<&SharedLibrary> {
+        match self {
+            Self::SharedLibrary(so) => Some(so),
+            Self::Normal(_) => None,
+        }
+    }
+
+    pub fn as_normal(&self) -> Option<&str> {
+        match self {
+            Self::SharedLibrary(_) => None,
+            Self::Normal(name) => Some(name),
+        }
+    }
+
+    /// Same as [`ToString::to_string`], writing shared libraries with `config`
+    pub fn to_string_with(&self, config: &ParserConfig) -> String {
+        match self {
+            Self::SharedLibrary(so) => so.to_string_with(config),
+            Self::Normal(name) => name.clone(),
+        }
+    }
+}
+
+impl ContentsPath {
+    pub fn new(parent: PathBuf, file: File) -> Self {
+        Self {
+            parent,
+            file,
+        }
+    }
+
+    /// Parent directory, empty for files at the archive root
+    pub fn get_parent(&self) -> &Path {
+        &self.parent
+    }
+
+    pub fn get_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Join the parent directory and the file name
+    pub fn full_path(&self) -> PathBuf {
+        self.full_path_with(&ParserConfig::ELF)
+    }
+
+    /// Same as [`ContentsPath::full_path`], writing shared libraries with
+    /// `config`
+    pub fn full_path_with(&self, config: &ParserConfig) -> PathBuf {
+        self.parent.join(self.file.to_string_with(config))
+    }
+
+    /// Same as [`ToString::to_string`], writing shared libraries with `config`
+    pub fn to_string_with(&self, config: &ParserConfig) -> String {
+        self.full_path_with(config).to_string_lossy().to_string()
+    }
+}
+
+impl PackageName {
+    pub fn from_bytes(area: Option<&[u8]>, section: Option<&[u8]>, name: &[u8]) -> Self {
+        Self {
+            area: area.map(|a| String::from_utf8_lossy(a).to_string()),
+            section: section.map(|s| String::from_utf8_lossy(s).to_string()),
+            name: String::from_utf8_lossy(name).to_string(),
+        }
+    }
+
+    pub fn get_area(&self) -> Option<&str> {
+        self.area.as_deref()
+    }
+
+    pub fn get_section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl ContentsEntry {
+    pub fn new(path: ContentsPath, packages: Vec<PackageName>) -> Self {
+        Self {
+            path,
+            packages,
+        }
+    }
+
+    pub fn get_path(&self) -> &ContentsPath {
+        &self.path
+    }
+
+    pub fn get_packages(&self) -> &[PackageName] {
+        &self.packages
+    }
+
+    /// Parse a single line of a Contents file
+    ///
+    /// See [`take_line_owned`], which this forwards to.
+    pub fn parse(line: &str) -> Result<ContentsEntry, ContentsParseError> {
+        take_line_owned(line.as_bytes())
+    }
+
+    /// Split the entry into one `(path, package)` pair per package
+    ///
+    /// The path is cloned once for every package owning it.
+    pub fn flatten(self) -> impl Iterator<Item = (ContentsPath, PackageName)> {
+        let path = self.path;
+        self.packages.into_iter().map(move |package| (path.clone(), package))
+    }
+
+    /// Compare with `other`, treating the package lists as sets
+    ///
+    /// Unlike `==`, neither the order nor repetitions of packages matter.
+    pub fn eq_unordered(&self, other: &ContentsEntry) -> bool {
+        self.path == other.path
+            && self.packages.iter().all(|package| other.packages.contains(package))
+            && other.packages.iter().all(|package| self.packages.contains(package))
+    }
+}
+
+impl FromIterator<ContentsEntry> for ContentsIndex {
+    fn from_iter<I: IntoIterator<Item = ContentsEntry>>(iter: I) -> Self {
+        let mut entries: HashMap<String, ContentsEntry> = HashMap::new();
+        for entry in iter {
+            let key = ContentsIndex::normalize(&entry.path.to_string()).to_string();
+            match entries.get_mut(&key) {
+                Some(prev) => prev.packages.extend(entry.packages),
+                None => {
+                    entries.insert(key, entry);
+                }
+            }
+        }
+        Self {
+            entries,
+        }
+    }
+}
+
+impl FromIterator<ContentsEntry> for ContentsStats {
+    fn from_iter<I: IntoIterator<Item = ContentsEntry>>(iter: I) -> Self {
+        let mut ret = Self::default();
+        let mut packages = HashSet::new();
+        for entry in iter {
+            ret.entries += 1;
+            if entry.path.file.is_shared_library() {
+                ret.shared_libraries += 1;
+            }
+            packages.extend(entry.packages.into_iter().map(|package| package.name));
+        }
+        ret.packages = packages.len();
+        ret
+    }
+}
+
+impl ContentsStats {
+    pub fn get_entries(&self) -> usize {
+        self.entries
+    }
+
+    pub fn get_shared_libraries(&self) -> usize {
+        self.shared_libraries
+    }
+
+    /// Number of distinct package names
+    pub fn get_packages(&self) -> usize {
+        self.packages
+    }
+}
+
+impl ContentsIndex {
+    #[inline]
+    fn normalize(path: &str) -> &str {
+        path.trim_start_matches("./")
+    }
+
+    /// Packages shipping `path`, empty if the path is unknown
+    pub fn which_packages(&self, path: &str) -> &[PackageName] {
+        self.entries
+            .get(Self::normalize(path))
+            .map(ContentsEntry::get_packages)
+            .unwrap_or_default()
+    }
+
+    /// Entry with the highest sover for each shared library name
+    ///
+    /// Keyed by [`SharedLibrary::get_name`], e.g. `libfoo` for both
+    /// `libfoo.so.1` and `libfoo.so.2`. Libraries with the same sover are
+    /// told apart by their first package name, then by path, so the result
+    /// does not depend on the order of the input.
+    pub fn newest_shared_libraries(&self) -> HashMap<String, &ContentsEntry> {
+        let mut ret: HashMap<String, (&SharedLibrary, &ContentsEntry)> = HashMap::new();
+        for entry in self.entries.values() {
+            let Some(so) = entry.path.file.as_shared_library() else {
+                continue;
+            };
+            let newer = match ret.get(so.get_name()) {
+                Some((prev_so, prev)) => so.cmp(prev_so).then_with(|| {
+                    let package = |entry: &ContentsEntry| entry.packages.first().map(|package| package.name.clone());
+                    package(prev)
+                        .cmp(&package(entry))
+                        .then_with(|| prev.path.to_string().cmp(&entry.path.to_string()))
+                }).is_gt(),
+                None => true,
+            };
+            if newer {
+                ret.insert(so.get_name().to_string(), (so, entry));
+            }
+        }
+        ret.into_iter().map(|(name, (_, entry))| (name, entry)).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{File, ContentsEntry, ContentsIndex, SharedLibrary, ContentsPath, PackageName, ContentsIterator, ContentsSharedLibraryIterator, ContentsResultIterator, ContentsRawIterator, ContentsParseError, ContentsError, ParserConfig, many0_path_segments, many0_sover_segment, take_file_so, take_file, take_path, take_line, take_line_so, take_line_with, take_line_owned, take_line_owned_with, take_line_bounded, take_line_bounded_with, parse_line, parse_line_with, take_package, take_packages, trim_line_ending, parse_all, parse_all_so, stats, is_section_name, is_package_name};
+    #[cfg(feature = "rayon")]
+    use super::par_parse;
+    #[cfg(feature = "fuzz")]
+    use super::fuzz_take_line;
+    use crate::{AcceptAllFilter, Filter};
+
+    use nom::error::ErrorKind;
+
+    #[cfg(any(feature = "gzip", not(debug_assertions)))]
+    use flate2::read::GzDecoder;
+    #[cfg(feature = "gzip")]
+    use flate2::{Compression, write::GzEncoder};
+
+    use std::cell::Cell;
+    use std::fs;
+    use std::env;
+    use std::io::{self, Read};
+    use std::rc::Rc;
+    #[cfg(any(feature = "gzip", feature = "xz", feature = "bzip2"))]
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_many0_path_segments() {
+        assert_eq!(many0_path_segments(b"/usr/bin/bash "), Ok((&b"bash "[..], PathBuf::from("/usr/bin"))));
+        assert_eq!(many0_path_segments(b"/usr/bin "), Ok((&b"bin "[..], PathBuf::from("/usr"))));
+        assert_eq!(many0_path_segments(b"./usr/bin "), Ok((&b"bin "[..], PathBuf::from("./usr"))));
+    }
+
+    #[test]
+    fn test_many0_sover_segment() {
+        assert_eq!(many0_sover_segment(b".1.1.4 "), Ok((&b" "[..], vec![1, 1, 4])));
+        assert_eq!(many0_sover_segment(b" "), Ok((&b" "[..], vec![])));
+    }
+
+    #[test]
+    fn test_take_file_so() {
+        assert_eq!(take_file_so(b"libnuma.so.1.1.4 "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
+            name: "libnuma".to_string(),
+            sover: vec![1, 1, 4],
+        }))));
+        assert_eq!(take_file_so(b"libnuma.so.1.1.4.5.1.4 "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
+            name: "libnuma".to_string(),
+            sover: vec![1, 1, 4, 5, 1, 4],
+        }))));
+        assert_eq!(take_file_so(b"libnuma.so "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
+            name: "libnuma".to_string(),
+            sover: vec![],
+        }))));
+        assert!(take_file_so(b"bash ").is_err());
+    }
+
+    #[test]
+    fn test_take_file() {
+        assert_eq!(take_file(b"libnuma.so.1.1.4 "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
+            name: "libnuma".to_string(),
+            sover: vec![1, 1, 4],
         }))));
         assert_eq!(take_file(b"libnuma.so.1.1.4.5.1.4 "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
             name: "libnuma".to_string(),
@@ -400,6 +1344,68 @@ mod test {
         assert_eq!(take_file(b"bash "), Ok((&b""[..], File::Normal("bash".to_string()))));
     }
 
+    #[test]
+    fn test_parser_config_dylib() {
+        let config = ParserConfig::MACHO;
+        assert_eq!(ParserConfig::default(), ParserConfig::ELF);
+        let line = b"usr/lib/libfoo.1.2.dylib   libs/foo\n";
+        let (_, entry) = take_line_with(&config, line).unwrap();
+        let entry = entry.unwrap();
+        let so = SharedLibrary{
+            name: "libfoo".to_string(),
+            sover: vec![1, 2],
+        };
+        assert_eq!(entry.get_path().get_file(), &File::SharedLibrary(so.clone()));
+        assert_eq!(so.to_string_with(&config), "libfoo.1.2.dylib");
+        assert_eq!(so.to_string(), "libfoo.so.1.2");
+        assert_eq!(entry.to_string_with(&config).as_bytes(), trim_line_ending(line));
+        assert_eq!(parse_line_with(&config, b"usr/lib/libfoo.dylib\tlibs/foo").unwrap().get_path().get_file(), &File::SharedLibrary(SharedLibrary{
+            name: "libfoo".to_string(),
+            sover: vec![],
+        }));
+        assert_eq!(parse_line_with(&config, b"usr/lib/libfoo.so.1\tlibs/foo").unwrap().get_path().get_file(), &File::Normal("libfoo.so.1".to_string()));
+        assert_eq!(take_line_owned_with(&config, line).unwrap(), entry);
+        assert_eq!(take_line_bounded_with(&config, line, 64).unwrap(), entry);
+
+        let custom = ParserConfig::new().with_soname_separator(".sl").with_sover_separator("-");
+        let (_, custom_entry) = take_line_with(&custom, b"usr/lib/libfoo.sl-1-2   libs/foo\n").unwrap();
+        assert_eq!(custom_entry.unwrap().get_path().to_string_with(&custom), "usr/lib/libfoo.sl-1-2");
+
+        let input = b"usr/lib/libbar.1.dylib   libs/bar\nusr/lib/libfoo.so.1   libs/foo\n";
+        let result: Vec<ContentsEntry> = ContentsSharedLibraryIterator::new(&input[..], AcceptAllFilter::new())
+            .with_config(config.clone())
+            .collect();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].get_path().get_file().as_shared_library().map(SharedLibrary::get_name), Some("libbar"));
+        let result: Vec<_> = ContentsResultIterator::new(&input[..], AcceptAllFilter::new())
+            .with_config(config.clone())
+            .collect();
+        assert!(result[0].as_ref().unwrap().get_path().get_file().is_shared_library());
+        assert!(!result[1].as_ref().unwrap().get_path().get_file().is_shared_library());
+        let result: Vec<_> = ContentsRawIterator::new(&input[..], AcceptAllFilter::new())
+            .with_config(config.clone())
+            .collect();
+        assert!(result[0].0.get_path().get_file().is_shared_library());
+        #[cfg(feature = "rayon")]
+        {
+            let result = super::par_parse_with(&input[..], AcceptAllFilter::new(), &config).unwrap();
+            assert!(result[0].get_path().get_file().is_shared_library());
+            assert!(!result[1].get_path().get_file().is_shared_library());
+        }
+    }
+
+    #[test]
+    fn test_take_file_non_numeric_sover() {
+        assert!(take_file_so(b"libfoo.so.1a ").is_err());
+        assert!(take_file_so(b"libfoo.so.1.2-rc ").is_err());
+        assert_eq!(take_file(b"libfoo.so.1a "), Ok((&b""[..], File::Normal("libfoo.so.1a".to_string()))));
+        assert_eq!(take_file(b"libfoo.so.1.2-rc "), Ok((&b""[..], File::Normal("libfoo.so.1.2-rc".to_string()))));
+        assert_eq!(take_file(b"libfoo.so.1.2.3 "), Ok((&b""[..], File::SharedLibrary(SharedLibrary{
+            name: "libfoo".to_string(),
+            sover: vec![1, 2, 3],
+        }))));
+    }
+
     #[test]
     fn test_take_path() {
         assert_eq!(take_path(b"./usr/lib/libnuma.so.1.1.4 "), Ok((&b""[..], ContentsPath {
@@ -426,6 +1432,22 @@ mod test {
         })));
     }
 
+    #[test]
+    fn test_contents_path_getters() {
+        let (_, path) = take_path(b"./usr/lib/libnuma.so.1.1.4 ").unwrap();
+        assert_eq!(path.get_parent(), Path::new("./usr/lib/"));
+        assert_eq!(path.get_file(), &File::SharedLibrary(SharedLibrary {
+            name: "libnuma".to_string(),
+            sover: vec![1, 1, 4],
+        }));
+        assert_eq!(path.full_path(), PathBuf::from("./usr/lib/libnuma.so.1.1.4"));
+
+        let (_, path) = take_path(b"bash ").unwrap();
+        assert_eq!(path.get_parent(), Path::new(""));
+        assert_eq!(path.get_file(), &File::Normal("bash".to_string()));
+        assert_eq!(path.full_path(), PathBuf::from("bash"));
+    }
+
     #[test]
     fn test_take_package() {
         assert_eq!(take_package(b"zsh\n"), Ok((&b"\n"[..], PackageName {
@@ -445,6 +1467,53 @@ mod test {
         })));
     }
 
+    #[test]
+    fn test_name_bytes() {
+        for chr in 0..=u8::MAX {
+            let section = chr.is_ascii_alphanumeric() || chr == b'-';
+            let package = chr.is_ascii_lowercase() || chr.is_ascii_digit() || b"+-_.".contains(&chr);
+            assert_eq!(is_section_name(chr), section, "section byte {:#04x}", chr);
+            assert_eq!(is_package_name(chr), package, "package byte {:#04x}", chr);
+        }
+    }
+
+    #[test]
+    fn test_take_package_sections() {
+        assert_eq!(take_package(b"non-free-firmware/devel/cuda\n"), Ok((&b"\n"[..], PackageName {
+            area: Some("non-free-firmware".to_string()),
+            section: Some("devel".to_string()),
+            name: "cuda".to_string(),
+        })));
+        assert_eq!(take_package(b"Contrib/3rdparty/libfoo2\n"), Ok((&b"\n"[..], PackageName {
+            area: Some("Contrib".to_string()),
+            section: Some("3rdparty".to_string()),
+            name: "libfoo2".to_string(),
+        })));
+    }
+
+    #[test]
+    fn test_take_package_uppercase() {
+        let err = nom::Err::Failure(nom::error::Error::new(&b"Ash\n"[..], ErrorKind::Verify));
+        assert_eq!(take_package(b"shells/bAsh\n"), Err(err));
+        let err = nom::Err::Failure(nom::error::Error::new(&b"Bash\n"[..], ErrorKind::Verify));
+        assert_eq!(take_package(b"shells/Bash\n"), Err(err));
+        let err = take_line_owned(b"usr/bin/bash   shells/bash,shells/Zsh").unwrap_err();
+        assert_eq!(err.get_error(), ContentsError::MalformedPackage);
+    }
+
+    #[test]
+    fn test_package_name_getters() {
+        let (_, package) = take_package(b"non-free/devel/cuda\n").unwrap();
+        assert_eq!(package.get_area(), Some("non-free"));
+        assert_eq!(package.get_section(), Some("devel"));
+        assert_eq!(package.get_name(), "cuda");
+
+        let (_, package) = take_package(b"zsh\n").unwrap();
+        assert_eq!(package.get_area(), None);
+        assert_eq!(package.get_section(), None);
+        assert_eq!(package.get_name(), "zsh");
+    }
+
     #[test]
     fn test_take_packages() {
         assert_eq!(take_packages(b"shells/bash,shells/zsh\n"), Ok((&b"\n"[..], vec![
@@ -462,6 +1531,26 @@ mod test {
         )));
     }
 
+    #[test]
+    fn test_take_packages_whitespace() {
+        let expected = vec![
+            PackageName::from_bytes(None, Some(b"shells"), b"bash"),
+            PackageName::from_bytes(None, Some(b"shells"), b"zsh"),
+        ];
+        assert_eq!(take_packages(b"shells/bash, shells/zsh\n"), Ok((&b"\n"[..], expected.clone())));
+        assert_eq!(take_packages(b"shells/bash , shells/zsh \n"), Ok((&b"\n"[..], expected.clone())));
+
+        let (rest, entry) = take_line(b"usr/bin/bash   shells/bash, shells/zsh\n").unwrap();
+        assert_eq!(rest, b"\n");
+        assert_eq!(entry.unwrap().get_packages(), &expected[..]);
+        let (rest, entry) = take_line(b"usr/bin/bash shells/bash, shells/zsh\n").unwrap();
+        assert_eq!(rest, b"\n");
+        assert_eq!(entry.unwrap().get_path().to_string(), "usr/bin/bash");
+        let (rest, entry) = take_line(b"usr/bin/bash   shells/bash  \n").unwrap();
+        assert_eq!(rest, b"\n");
+        assert_eq!(entry.unwrap().to_string(), "usr/bin/bash   shells/bash");
+    }
+
     #[test]
     fn test_take_line_normal() {
         let input = b"./usr/bin/bash   shells/bash\n";
@@ -480,6 +1569,32 @@ mod test {
         }))));
     }
 
+    #[test]
+    fn test_take_line_spaces_in_path() {
+        let input = b"./usr/share/My App/data.bin   apps/myapp\n";
+        assert_eq!(take_line(input), Ok((&b"\n"[..], Some(ContentsEntry {
+            path: ContentsPath {
+                parent: PathBuf::from("./usr/share/My App"),
+                file: File::Normal("data.bin".to_string()),
+            },
+            packages: vec![
+                PackageName {
+                    area: None,
+                    section: Some("apps".to_string()),
+                    name: "myapp".to_string(),
+                }
+            ],
+        }))));
+        let input = b"./usr/share/doc/my app.txt\tapps/myapp\n";
+        assert_eq!(take_line(input).unwrap().1.unwrap().get_path().to_string(), "./usr/share/doc/my app.txt");
+    }
+
+    #[test]
+    fn test_take_line_no_separator() {
+        assert!(take_line(b"./usr/bin/bash\n").is_err());
+        assert!(take_line(b"").is_err());
+    }
+
     #[test]
     fn test_take_line_so() {
         let input = b"./usr/lib/libnuma.so.1.1.4   admin/numactl\n";
@@ -501,6 +1616,46 @@ mod test {
         }))));
     }
 
+    #[test]
+    fn test_take_line_so_multiarch() {
+        let input = b"./usr/lib/x86_64-linux-gnu/libnuma.so.1.1.4   admin/numactl\n";
+        assert_eq!(take_line_so(input), Ok((&b"\n"[..], Some(ContentsEntry {
+            path: ContentsPath {
+                parent: PathBuf::from("./usr/lib/x86_64-linux-gnu"),
+                file: File::SharedLibrary(SharedLibrary {
+                    name: "libnuma".to_string(),
+                    sover: vec![1, 1, 4],
+                }),
+            },
+            packages: vec![
+                PackageName {
+                    area: None,
+                    section: Some("admin".to_string()),
+                    name: "numactl".to_string(),
+                }
+            ],
+        }))));
+    }
+
+    #[test]
+    fn test_sharedlibrary_ordering() {
+        let lib = |name: &str, sover: Vec<usize>| SharedLibrary::from_bytes(name.as_bytes(), sover);
+        assert!(lib("libfoo", vec![1, 2]) < lib("libfoo", vec![1, 2, 1]));
+        assert!(lib("libfoo", vec![1, 10]) > lib("libfoo", vec![1, 9, 9]));
+        assert!(lib("libfoo", vec![2]) > lib("libfoo", vec![1, 9]));
+        assert!(lib("libfoo", vec![]) < lib("libfoo", vec![0, 1]));
+        assert!(lib("libbar", vec![9]) < lib("libfoo", vec![1]));
+        assert!(lib("libfoo", vec![1, 2]) < lib("libfoo", vec![1, 2, 0]));
+        assert_eq!(lib("libfoo", vec![1, 2]).cmp(&lib("libfoo", vec![1, 2])), std::cmp::Ordering::Equal);
+
+        let newest = [vec![1, 2], vec![1, 2, 1], vec![0, 9]]
+            .into_iter()
+            .map(|sover| lib("libfoo", sover))
+            .max()
+            .unwrap();
+        assert_eq!(newest.get_sover(), &[1, 2, 1]);
+    }
+
     #[test]
     fn test_sharedlibrary_to_string() {
         assert_eq!(SharedLibrary {
@@ -531,6 +1686,42 @@ mod test {
         })).to_string(), "/usr/lib/libnuma.so.1.1.4.5.1.4");
     }
 
+    #[test]
+    fn test_package_name_to_string() {
+        assert_eq!(PackageName::from_bytes(Some(b"non-free"), Some(b"devel"), b"cuda").to_string(), "non-free/devel/cuda");
+        assert_eq!(PackageName::from_bytes(None, Some(b"shells"), b"zsh").to_string(), "shells/zsh");
+        assert_eq!(PackageName::from_bytes(None, None, b"zsh").to_string(), "zsh");
+    }
+
+    #[test]
+    fn test_contents_entry_round_trip() {
+        for line in [
+            "./usr/bin/bash   shells/bash",
+            "./usr/lib/libnuma.so.1.1.4   admin/numactl",
+            "usr/bin/zsh   shells/zsh,shells/zsh-static",
+            "usr/lib/libcuda.so.1   non-free/devel/cuda",
+        ] {
+            let (_, entry) = take_line(format!("{}\n", line).as_bytes()).unwrap();
+            assert_eq!(entry.unwrap().to_string(), line);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_contents_entry_serde() {
+        let (_, entry) = take_line(b"./usr/lib/libnuma.so.1.1.4   admin/numactl,non-free/devel/cuda\n").unwrap();
+        let entry = entry.unwrap();
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(r#""SharedLibrary":{"name":"libnuma","sover":[1,1,4]}"#));
+        assert_eq!(serde_json::from_str::<ContentsEntry>(&json).unwrap(), entry);
+
+        let (_, entry) = take_line(b"usr/bin/bash   shells/bash\n").unwrap();
+        let entry = entry.unwrap();
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(r#""Normal":"bash""#));
+        assert_eq!(serde_json::from_str::<ContentsEntry>(&json).unwrap(), entry);
+    }
+
     #[test]
     fn test_parser_dummy() {
         let file = fs::File::open(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
@@ -539,6 +1730,336 @@ mod test {
         assert_eq!(result.len(), 19);
     }
 
+    /// Filter counting the blank lines it is shown
+    #[derive(Default)]
+    struct BlankCounter {
+        accept_empty: bool,
+        blanks: Rc<Cell<usize>>,
+    }
+
+    impl Filter for BlankCounter {
+        fn filter_bytes(&self, input: &[u8]) -> bool {
+            if input.iter().all(u8::is_ascii_whitespace) {
+                self.blanks.set(self.blanks.get() + 1);
+            }
+            true
+        }
+
+        fn accept_empty(&self) -> bool {
+            self.accept_empty
+        }
+    }
+
+    #[test]
+    fn test_filter_accept_empty() {
+        let input = b"usr/bin/bash   shells/bash\n\nusr/bin/zsh   shells/zsh\n";
+        let filter = BlankCounter::default();
+        let blanks = filter.blanks.clone();
+        assert_eq!(ContentsIterator::new(&input[..], filter).count(), 2);
+        assert_eq!(blanks.get(), 0);
+        let filter = BlankCounter { accept_empty: true, ..Default::default() };
+        let blanks = filter.blanks.clone();
+        assert_eq!(ContentsIterator::new(&input[..], filter).count(), 2);
+        assert_eq!(blanks.get(), 1);
+    }
+
+    #[test]
+    fn test_take_line_bounded() {
+        let line = format!("{}bash   shells/bash\n", "usr/".repeat(1024));
+        let err = take_line_bounded(line.as_bytes(), 1024).unwrap_err();
+        assert_eq!(err.get_error(), ContentsError::TooLarge);
+        assert_eq!(err.get_line().len(), 1024);
+        assert!(take_line_bounded(line.as_bytes(), line.len()).is_ok());
+        assert!(take_line_bounded(b"usr/bin/bash   shells/bash\r\n", 26).is_ok());
+        assert_eq!(take_line_bounded(b"usr/bin/broken\n", 1024).unwrap_err().get_error(), ContentsError::UnexpectedEof);
+    }
+
+    /// Feed pseudo-random lines, drawn from the bytes found in Contents files,
+    /// to the bounded parser
+    #[cfg(feature = "fuzz")]
+    #[test]
+    fn fuzz_take_line_bounded() {
+        const ALPHABET: &[u8] = b"abcXYZ019./-_+,: \t\r\n";
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..10_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let len = (state % 8192) as usize;
+            let line: Vec<u8> = (0..len)
+                .map(|i| ALPHABET[((state >> (i % 56)) as usize + i) % ALPHABET.len()])
+                .collect();
+            fuzz_take_line(&line);
+        }
+    }
+
+    #[test]
+    fn test_take_line_root_file() {
+        let entry = ContentsEntry::parse("foo   sect/pkg").unwrap();
+        assert_eq!(entry.get_path().get_parent(), Path::new(""));
+        assert_eq!(entry.get_path().get_file(), &File::normal(b"foo"));
+        assert_eq!(entry.get_path().to_string(), "foo");
+        assert_eq!(entry.get_path().full_path(), PathBuf::from("foo"));
+        assert_eq!(ContentsEntry::parse("./foo   sect/pkg").unwrap().get_path().to_string(), "./foo");
+    }
+
+    #[test]
+    fn test_file_accessors() {
+        let so = File::so(b"libfoo", vec![1, 2]);
+        assert!(so.is_shared_library());
+        assert_eq!(so.as_shared_library(), Some(&SharedLibrary::from_bytes(b"libfoo", vec![1, 2])));
+        assert_eq!(so.as_normal(), None);
+        let normal = File::normal(b"bash");
+        assert!(!normal.is_shared_library());
+        assert_eq!(normal.as_shared_library(), None);
+        assert_eq!(normal.as_normal(), Some("bash"));
+    }
+
+    #[test]
+    fn test_parser_crlf() {
+        let input = b"usr/bin/bash   shells/bash\r\nusr/bin/zsh   shells/zsh,shells/zsh-static\r\n";
+        let result: Vec<ContentsEntry> = ContentsIterator::new(&input[..], AcceptAllFilter::new()).collect();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].get_packages()[0].get_name(), "bash");
+        assert_eq!(result[1].get_packages()[1].get_name(), "zsh-static");
+        assert_eq!(result[1].get_path().to_string(), "usr/bin/zsh");
+        let results: Vec<ContentsEntry> = ContentsResultIterator::new(&input[..], AcceptAllFilter::new()).map(Result::unwrap).collect();
+        assert_eq!(results, result);
+        #[cfg(feature = "rayon")]
+        assert_eq!(par_parse(&input[..], AcceptAllFilter::new()).unwrap(), result);
+        assert_eq!(trim_line_ending(b"usr/bin/zsh\r\n"), b"usr/bin/zsh");
+        assert_eq!(trim_line_ending(b"usr/bin/zsh\r"), b"usr/bin/zsh");
+        assert_eq!(trim_line_ending(b"usr/bin/zsh"), b"usr/bin/zsh");
+    }
+
+    #[test]
+    fn test_parse_all_dummy() {
+        let path = format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap());
+        let result = parse_all(fs::File::open(&path).unwrap(), AcceptAllFilter::new());
+        let expected: Vec<ContentsEntry> = ContentsIterator::new(fs::File::open(&path).unwrap(), AcceptAllFilter::new()).collect();
+        assert_eq!(result.len(), 19);
+        assert_eq!(result, expected);
+        assert_eq!(parse_all_so(fs::File::open(&path).unwrap(), AcceptAllFilter::new()).len(), 18);
+    }
+
+    #[test]
+    fn test_stats_dummy() {
+        let path = format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap());
+        let stats = stats(ContentsIterator::new(fs::File::open(&path).unwrap(), AcceptAllFilter::new()));
+        assert_eq!(stats.get_entries(), 19);
+        assert_eq!(stats.get_shared_libraries(), 18);
+        assert_eq!(stats.get_packages(), 8);
+        assert_eq!(stats, ContentsIterator::new(fs::File::open(&path).unwrap(), AcceptAllFilter::new()).collect());
+    }
+
+    #[test]
+    fn test_parser_dummy_entry_filter() {
+        let file = fs::File::open(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let parser = ContentsIterator::with_entry_filter(file, AcceptAllFilter::new(), |entry: &ContentsEntry| {
+            entry.get_packages().iter().any(|package| package.get_section() == Some("libs"))
+        });
+        let result: Vec<ContentsEntry> = parser.collect();
+        assert_eq!(result.len(), 11);
+    }
+
+    #[test]
+    fn test_contents_entry_parse() {
+        let entry = ContentsEntry::parse("usr/bin/bash   shells/bash").unwrap();
+        assert_eq!(entry.get_path().get_file(), &File::Normal("bash".to_string()));
+        assert_eq!(entry.get_packages(), &[PackageName::from_bytes(None, Some(b"shells"), b"bash")]);
+        assert_eq!(ContentsEntry::parse("usr/bin/bash   shells/bash\n").unwrap(), entry);
+
+        let entry = ContentsEntry::parse("./usr/lib/libnuma.so.1.1.4   admin/numactl\r\n").unwrap();
+        assert_eq!(entry.get_path().get_file(), &File::so(b"libnuma", vec![1, 1, 4]));
+
+        let err = ContentsEntry::parse("./usr/bin/broken").unwrap_err();
+        assert_eq!(err.get_line(), b"./usr/bin/broken");
+        assert_eq!(err.get_error(), ContentsError::UnexpectedEof);
+        let err = ContentsEntry::parse("usr/bin/bash   shells/bash (trailing)").unwrap_err();
+        assert_eq!(err.get_error(), ContentsError::Nom(ErrorKind::Eof));
+    }
+
+    #[test]
+    fn test_take_line_owned() {
+        let entry = {
+            let mut buf = b"./usr/lib/libnuma.so.1.1.4   admin/numactl\n".to_vec();
+            let entry = take_line_owned(&buf).unwrap();
+            buf.clear();
+            entry
+        };
+        assert_eq!(entry.to_string(), "./usr/lib/libnuma.so.1.1.4   admin/numactl");
+        assert_eq!(take_line_owned(b"usr/bin/bash   shells/bash").unwrap(), ContentsEntry::parse("usr/bin/bash   shells/bash\n").unwrap());
+        assert_eq!(take_line_owned(b"usr/bin/broken\n").unwrap_err().get_line(), b"usr/bin/broken");
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert_eq!(parse_line(b"usr/bin/bash   shells/bash\n").unwrap(), ContentsEntry::parse("usr/bin/bash   shells/bash").unwrap());
+        assert_eq!(parse_line(b"usr/bin/bash\n"), Err(ContentsError::UnexpectedEof));
+        assert_eq!(parse_line(b"usr/bin/bash   \n"), Err(ContentsError::UnexpectedEof));
+        assert_eq!(parse_line(b""), Err(ContentsError::UnexpectedEof));
+        assert_eq!(parse_line(b"usr/lib/   libs/foo"), Err(ContentsError::MalformedPath));
+        assert_eq!(parse_line(b"usr/bin/bash   shells/Bash"), Err(ContentsError::MalformedPackage));
+        assert_eq!(parse_line(b"usr/bin/bash   shells/,bash"), Err(ContentsError::MalformedPackage));
+        assert_eq!(parse_line(b"usr/bin/bash   shells/bash (trailing)"), Err(ContentsError::Nom(ErrorKind::Eof)));
+    }
+
+    #[test]
+    fn test_contents_entry_flatten() {
+        let (_, entry) = take_line(b"usr/bin/bash   shells/bash,shells/zsh\n").unwrap();
+        let path = ContentsPath::new(PathBuf::from("usr/bin"), File::Normal("bash".to_string()));
+        assert_eq!(entry.unwrap().flatten().collect::<Vec<_>>(), vec![
+            (path.clone(), PackageName::from_bytes(None, Some(b"shells"), b"bash")),
+            (path, PackageName::from_bytes(None, Some(b"shells"), b"zsh")),
+        ]);
+    }
+
+    #[test]
+    fn test_contents_entry_eq_unordered() {
+        let entry = ContentsEntry::parse("usr/bin/bash   shells/bash,shells/zsh").unwrap();
+        let swapped = ContentsEntry::parse("usr/bin/bash   shells/zsh,shells/bash").unwrap();
+        assert_ne!(entry, swapped);
+        assert!(entry.eq_unordered(&swapped));
+        assert!(swapped.eq_unordered(&entry));
+        assert!(!entry.eq_unordered(&ContentsEntry::parse("usr/bin/bash   shells/bash").unwrap()));
+        assert!(!entry.eq_unordered(&ContentsEntry::parse("usr/bin/zsh   shells/zsh,shells/bash").unwrap()));
+    }
+
+    #[test]
+    fn test_flat_entries() {
+        let input = b"usr/bin/bash   shells/bash,shells/zsh\nusr/bin/zsh   shells/zsh\n";
+        let result: Vec<(String, String)> = ContentsIterator::new(&input[..], AcceptAllFilter::new())
+            .flat_entries()
+            .map(|(path, package)| (path.to_string(), package.get_name().to_string()))
+            .collect();
+        assert_eq!(result, vec![
+            ("usr/bin/bash".to_string(), "bash".to_string()),
+            ("usr/bin/bash".to_string(), "zsh".to_string()),
+            ("usr/bin/zsh".to_string(), "zsh".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_contents_index_dummy() {
+        let file = fs::File::open(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let index: ContentsIndex = ContentsIterator::new(file, AcceptAllFilter::new()).collect();
+        assert_eq!(index.len(), 19);
+        assert!(!index.is_empty());
+        assert_eq!(index.which_packages("usr/lib/libnss3.so"), &[PackageName::from_bytes(None, Some(b"libs"), b"nss")]);
+        assert_eq!(index.which_packages("./usr/lib/libXtst.so.6"), &[PackageName::from_bytes(None, Some(b"x11"), b"x11-lib")]);
+        assert!(index.which_packages("usr/bin/zsh").is_empty());
+    }
+
+    #[test]
+    fn test_contents_index_newest_shared_libraries() {
+        let input = b"usr/lib/libfoo.so.1   libs/foo1\n\
+            usr/lib/libfoo.so.2   libs/foo2\n\
+            usr/lib/libbar.so.3   libs/bar-b\n\
+            usr/lib64/libbar.so.3   libs/bar-a\n\
+            usr/bin/bash   shells/bash\n";
+        let index: ContentsIndex = ContentsIterator::new(&input[..], AcceptAllFilter::new()).collect();
+        let newest = index.newest_shared_libraries();
+        assert_eq!(newest.len(), 2);
+        assert_eq!(newest["libfoo"].get_path().to_string(), "usr/lib/libfoo.so.2");
+        assert_eq!(newest["libbar"].get_packages()[0].get_name(), "bar-a");
+    }
+
+    #[test]
+    fn test_parser_bytes_read() {
+        for fixture in ["Contents-amd64-dummy", "Contents-amd64-header"] {
+            let path = format!("{}/tests/{}", env::var("CARGO_MANIFEST_DIR").unwrap(), fixture);
+            let len = fs::metadata(&path).unwrap().len();
+            let mut parser = ContentsSharedLibraryIterator::new(fs::File::open(&path).unwrap(), AcceptAllFilter::new())
+                .skip_header(fixture.ends_with("header"));
+            assert_eq!(parser.bytes_read(), 0);
+            parser.next().unwrap();
+            assert!(parser.bytes_read() > 0 && parser.bytes_read() < len);
+            parser.by_ref().count();
+            assert_eq!(parser.bytes_read(), len);
+        }
+    }
+
+    #[test]
+    fn test_parser_take_entries() {
+        let path = format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap());
+        let parser = ContentsIterator::new(fs::File::open(&path).unwrap(), AcceptAllFilter::new());
+        assert_eq!(parser.take_entries(5).count(), 5);
+        let parser = ContentsSharedLibraryIterator::new(fs::File::open(&path).unwrap(), AcceptAllFilter::new());
+        assert_eq!(parser.take_entries(100).count(), 18);
+    }
+
+    #[test]
+    fn test_parser_skip_header() {
+        let path = format!("{}/tests/Contents-amd64-header", env::var("CARGO_MANIFEST_DIR").unwrap());
+        let parser = ContentsIterator::new(fs::File::open(&path).unwrap(), AcceptAllFilter::new()).skip_header(true);
+        let result: Vec<String> = parser.map(|entry| entry.to_string()).collect();
+        assert_eq!(result, vec![
+            "./usr/lib/libnss3.so   libs/nss",
+            "usr/lib/libnotify.so.4   libs/libnotify",
+            "usr/bin/bash   shells/bash",
+        ]);
+        let parser = ContentsSharedLibraryIterator::new(fs::File::open(&path).unwrap(), AcceptAllFilter::new()).skip_header(true);
+        assert_eq!(parser.count(), 2);
+        let parser = ContentsIterator::new(&b"usr/bin/bash   shells/bash\n"[..], AcceptAllFilter::new()).skip_header(true);
+        assert_eq!(parser.count(), 0);
+    }
+
+    #[test]
+    fn test_result_iterator() {
+        let input = b"./usr/bin/bash   shells/bash\n./usr/bin/broken\n\nusr/bin/zsh   shells/zsh\n";
+        let result: Vec<Result<ContentsEntry, ContentsParseError>> = ContentsResultIterator::new(&input[..], AcceptAllFilter::new()).collect();
+        assert_eq!(result.len(), 3);
+        assert!(result[0].is_ok());
+        let err = result[1].as_ref().unwrap_err();
+        assert_eq!(err.get_line(), b"./usr/bin/broken\n");
+        assert_eq!(err.get_error(), ContentsError::UnexpectedEof);
+        assert!(result[2].is_ok());
+
+        let filter = BlankCounter { accept_empty: true, ..Default::default() };
+        let blanks = filter.blanks.clone();
+        let result: Vec<Result<ContentsEntry, ContentsParseError>> = ContentsResultIterator::new(&input[..], filter).collect();
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[2].as_ref().unwrap_err().get_line(), b"\n");
+        assert_eq!(blanks.get(), 1);
+    }
+
+    #[test]
+    fn test_result_iterator_io_error() {
+        struct Truncated;
+
+        impl Read for Truncated {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated"))
+            }
+        }
+
+        let input = (&b"usr/bin/bash   shells/bash\nusr/bin/zs"[..]).chain(Truncated);
+        let mut parser = ContentsResultIterator::new(input, AcceptAllFilter::new());
+        assert!(parser.next().unwrap().is_ok());
+        let err = parser.next().unwrap().unwrap_err();
+        assert_eq!(err.get_error(), ContentsError::Io(io::ErrorKind::UnexpectedEof));
+        assert_eq!(err.get_line(), b"usr/bin/zs");
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn test_raw_iterator() {
+        let input = b"./usr/bin/bash   shells/bash\n./usr/bin/broken\nusr/bin/zsh   shells/zsh,shells/zsh-static\r\n";
+        let result: Vec<(ContentsEntry, Vec<u8>)> = ContentsRawIterator::new(&input[..], AcceptAllFilter::new()).collect();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].1, b"./usr/bin/bash   shells/bash\n");
+        assert_eq!(result[0].0, ContentsEntry::parse("./usr/bin/bash   shells/bash").unwrap());
+        assert_eq!(result[1].1, b"usr/bin/zsh   shells/zsh,shells/zsh-static\r\n");
+        assert_eq!(result[1].0.get_packages().len(), 2);
+    }
+
+    #[test]
+    fn test_result_iterator_dummy() {
+        let file = fs::File::open(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let parser = ContentsResultIterator::new(file, AcceptAllFilter::new());
+        assert!(parser.collect::<Result<Vec<ContentsEntry>, ContentsParseError>>().is_ok());
+    }
+
     #[test]
     fn test_parser_dummy_so() {
         let file = fs::File::open(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
@@ -547,6 +2068,97 @@ mod test {
         assert_eq!(result.len(), 18);
     }
 
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_parser_dummy_gzip() {
+        let plain = fs::read(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&plain).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let parser: ContentsIterator<GzDecoder<&[u8]>, _> = ContentsIterator::new_gzip(&compressed[..], AcceptAllFilter::new());
+        let result: Vec<ContentsEntry> = parser.collect();
+        assert_eq!(result, ContentsIterator::new(&plain[..], AcceptAllFilter::new()).collect::<Vec<ContentsEntry>>());
+        let parser = ContentsSharedLibraryIterator::new_gzip(&compressed[..], AcceptAllFilter::new());
+        assert_eq!(parser.count(), 18);
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_parser_dummy_xz() {
+        let plain = fs::read(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(&plain).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let result: Vec<ContentsEntry> = ContentsIterator::new_xz(&compressed[..], AcceptAllFilter::new()).collect();
+        assert_eq!(result, ContentsIterator::new(&plain[..], AcceptAllFilter::new()).collect::<Vec<ContentsEntry>>());
+        assert_eq!(ContentsSharedLibraryIterator::new_xz(&compressed[..], AcceptAllFilter::new()).count(), 18);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_parser_dummy_zstd() {
+        let plain = fs::read(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let compressed = zstd::stream::encode_all(&plain[..], 0).unwrap();
+
+        let result: Vec<ContentsEntry> = ContentsIterator::new_zstd(&compressed[..], AcceptAllFilter::new()).unwrap().collect();
+        assert_eq!(result, ContentsIterator::new(&plain[..], AcceptAllFilter::new()).collect::<Vec<ContentsEntry>>());
+        assert_eq!(ContentsSharedLibraryIterator::new_zstd(&compressed[..], AcceptAllFilter::new()).unwrap().count(), 18);
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn test_parser_dummy_bzip2() {
+        let plain = fs::read(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(&plain).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let result: Vec<ContentsEntry> = ContentsIterator::new_bzip2(&compressed[..], AcceptAllFilter::new()).collect();
+        assert_eq!(result, ContentsIterator::new(&plain[..], AcceptAllFilter::new()).collect::<Vec<ContentsEntry>>());
+        assert_eq!(ContentsSharedLibraryIterator::new_bzip2(&compressed[..], AcceptAllFilter::new()).count(), 18);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_parse_dummy() {
+        let file = fs::File::open(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let result = par_parse(file, AcceptAllFilter::new()).unwrap();
+        let file = fs::File::open(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let expected: Vec<ContentsEntry> = ContentsIterator::new(file, AcceptAllFilter::new()).collect();
+        assert_eq!(result, expected);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_parse_filter_sees_newline() {
+        let input = b"usr/bin/bash   shells/bash\n\nusr/bin/zsh   shells/zsh";
+        let filter = || crate::RegexFilter::new(r"\n$").unwrap();
+        let expected: Vec<ContentsEntry> = ContentsIterator::new(&input[..], filter()).collect();
+        assert_eq!(expected.len(), 1);
+        assert_eq!(par_parse(&input[..], filter()).unwrap(), expected);
+    }
+
+    #[cfg(all(feature = "rayon", not(debug_assertions)))]
+    #[test]
+    fn test_par_parse() {
+        use std::time::Instant;
+
+        let fs = fs::File::open(format!("{}/tests/Contents-amd64.gz", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let start = Instant::now();
+        let sequential: Vec<ContentsEntry> = ContentsIterator::new(GzDecoder::new(fs), AcceptAllFilter::new()).collect();
+        println!("ContentsIterator: {:?}", start.elapsed());
+
+        let fs = fs::File::open(format!("{}/tests/Contents-amd64.gz", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let start = Instant::now();
+        let parallel = par_parse(GzDecoder::new(fs), AcceptAllFilter::new()).unwrap();
+        println!("par_parse: {:?}", start.elapsed());
+
+        assert_eq!(parallel.len(), 4411104);
+        assert_eq!(parallel, sequential);
+    }
+
     #[cfg(not(debug_assertions))]
     #[test]
     fn test_parser() {
@@ -0,0 +1,309 @@
+//! Ready-made filters for the APT metadata iterators
+
+use regex::bytes::Regex;
+
+use std::collections::HashSet;
+
+use crate::contents::{find_column_separator, ContentsEntry, EntryFilter};
+use crate::Filter;
+
+/// Filter accepting lines matched by a regular expression
+///
+/// The pattern is matched against the raw bytes of each line, so no UTF-8
+/// conversion happens on the hot path.
+///
+/// ```
+/// use apt_parser::RegexFilter;
+/// use apt_parser::contents::ContentsIterator;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/Contents-amd64-dummy"))?;
+/// let entries: Vec<_> = ContentsIterator::new(file, RegexFilter::new("usr/lib")?).collect();
+/// assert_eq!(entries.len(), 18);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RegexFilter {
+    regex: Regex,
+}
+
+impl Filter for RegexFilter {
+    fn filter_bytes(&self, input: &[u8]) -> bool {
+        self.regex.is_match(input)
+    }
+}
+
+impl RegexFilter {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: Regex::new(pattern)?,
+        })
+    }
+}
+
+impl From<Regex> for RegexFilter {
+    fn from(regex: Regex) -> Self {
+        Self {
+            regex,
+        }
+    }
+}
+
+/// Filter accepting lines whose path ends with one of the extensions
+///
+/// Only the column separator is located, the line is not parsed.
+/// Extensions may be given with or without their leading dot.
+#[derive(Clone, Debug, Default)]
+pub struct ExtensionFilter {
+    extensions: Vec<Vec<u8>>,
+}
+
+impl Filter for ExtensionFilter {
+    fn filter_bytes(&self, input: &[u8]) -> bool {
+        match find_column_separator(input) {
+            Some(separate) => {
+                let path = &input[..separate];
+                self.extensions.iter().any(|extension| path.ends_with(extension))
+            }
+            None => false,
+        }
+    }
+}
+
+impl ExtensionFilter {
+    pub fn new<S: AsRef<str>>(extensions: Vec<S>) -> Self {
+        Self {
+            extensions: extensions
+                .into_iter()
+                .map(|extension| format!(".{}", extension.as_ref().trim_start_matches('.')).into_bytes())
+                .collect(),
+        }
+    }
+}
+
+/// Entry filter accepting entries owned by a package in one of the sections
+///
+/// Entries are accepted when any of their packages matches, packages without
+/// a section never match.
+#[derive(Clone, Debug, Default)]
+pub struct SectionFilter {
+    sections: HashSet<String>,
+}
+
+impl EntryFilter for SectionFilter {
+    fn accept(&self, entry: &ContentsEntry) -> bool {
+        entry
+            .get_packages()
+            .iter()
+            .filter_map(|package| package.get_section())
+            .any(|section| self.sections.contains(section))
+    }
+}
+
+impl SectionFilter {
+    pub fn new(sections: HashSet<String>) -> Self {
+        Self {
+            sections,
+        }
+    }
+}
+
+/// Filter accepting lines accepted by both of its members
+///
+/// The second filter is only evaluated when the first one accepts the line.
+#[derive(Clone, Debug, Default)]
+pub struct AndFilter<A, B> {
+    first: A,
+    second: B,
+}
+
+/// Filter accepting lines accepted by either of its members
+///
+/// The second filter is only evaluated when the first one rejects the line.
+#[derive(Clone, Debug, Default)]
+pub struct OrFilter<A, B> {
+    first: A,
+    second: B,
+}
+
+/// Filter inverting the decision of its member
+#[derive(Clone, Debug, Default)]
+pub struct NotFilter<F> {
+    inner: F,
+}
+
+/// Builder-style helpers for composing filters
+pub trait FilterExt: Filter + Sized {
+    fn and<B: Filter>(self, other: B) -> AndFilter<Self, B> {
+        AndFilter::new(self, other)
+    }
+
+    fn or<B: Filter>(self, other: B) -> OrFilter<Self, B> {
+        OrFilter::new(self, other)
+    }
+
+    fn not(self) -> NotFilter<Self> {
+        NotFilter::new(self)
+    }
+}
+
+impl<F: Filter> FilterExt for F {}
+
+impl<A: Filter, B: Filter> Filter for AndFilter<A, B> {
+    fn filter_bytes(&self, input: &[u8]) -> bool {
+        self.first.filter_bytes(input) && self.second.filter_bytes(input)
+    }
+
+    fn accept_empty(&self) -> bool {
+        self.first.accept_empty() || self.second.accept_empty()
+    }
+}
+
+impl<A: Filter, B: Filter> Filter for OrFilter<A, B> {
+    fn filter_bytes(&self, input: &[u8]) -> bool {
+        self.first.filter_bytes(input) || self.second.filter_bytes(input)
+    }
+
+    fn accept_empty(&self) -> bool {
+        self.first.accept_empty() || self.second.accept_empty()
+    }
+}
+
+impl<F: Filter> Filter for NotFilter<F> {
+    fn filter_bytes(&self, input: &[u8]) -> bool {
+        !self.inner.filter_bytes(input)
+    }
+
+    fn accept_empty(&self) -> bool {
+        self.inner.accept_empty()
+    }
+}
+
+impl<A: Filter, B: Filter> AndFilter<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+        }
+    }
+}
+
+impl<A: Filter, B: Filter> OrFilter<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+        }
+    }
+}
+
+impl<F: Filter> NotFilter<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AndFilter, ExtensionFilter, FilterExt, NotFilter, OrFilter, RegexFilter, SectionFilter};
+    use crate::contents::{ContentsIterator, EntryFilter};
+    use crate::{AcceptAllFilter, Filter};
+
+    use std::cell::Cell;
+    use std::collections::HashSet;
+    use std::env;
+    use std::fs;
+
+    /// Filter recording whether it has been consulted
+    struct SpyFilter<'a> {
+        called: &'a Cell<bool>,
+    }
+
+    impl Filter for SpyFilter<'_> {
+        fn filter_bytes(&self, _input: &[u8]) -> bool {
+            self.called.set(true);
+            true
+        }
+    }
+
+    #[test]
+    fn test_regex_filter() {
+        let filter = RegexFilter::new(r"^\./usr/lib/").unwrap();
+        assert!(filter.filter_bytes(b"./usr/lib/libnss3.so   libs/nss\n"));
+        assert!(!filter.filter_bytes(b"usr/bin/bash   shells/bash\n"));
+    }
+
+    #[test]
+    fn test_extension_filter() {
+        let filter = ExtensionFilter::new(vec![".h", "pc"]);
+        assert!(filter.filter_bytes(b"usr/include/nss/nss.h   libs/nss\n"));
+        assert!(!filter.filter_bytes(b"usr/bin/bash   shells/bash\n"));
+        assert!(!filter.filter_bytes(b"usr/bin/bash\n"));
+
+        let file = fs::File::open(format!("{}/tests/Contents-amd64-extensions", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let result: Vec<String> = ContentsIterator::new(file, filter)
+            .map(|entry| entry.get_path().to_string())
+            .collect();
+        assert_eq!(result, vec![
+            "usr/include/nss/nss.h",
+            "usr/include/libnotify/notify.h",
+            "usr/lib/pkgconfig/nss.pc",
+        ]);
+
+        let file = fs::File::open(format!("{}/tests/Contents-amd64-extensions", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        assert_eq!(ContentsIterator::new(file, ExtensionFilter::new(vec!["so"])).count(), 1);
+    }
+
+    #[test]
+    fn test_section_filter() {
+        let sections = HashSet::from(["libs".to_string(), "x11".to_string()]);
+        let file = fs::File::open(format!("{}/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        let parser = ContentsIterator::with_entry_filter(file, AcceptAllFilter::new(), SectionFilter::new(sections.clone()));
+        let result: Vec<_> = parser.collect();
+        assert_eq!(result.len(), 14);
+        let filter = SectionFilter::new(sections);
+        assert!(result.iter().all(|entry| filter.accept(entry)));
+    }
+
+    #[test]
+    fn test_regex_filter_invalid_pattern() {
+        assert!(RegexFilter::new("usr/(lib").is_err());
+    }
+
+    #[test]
+    fn test_and_filter() {
+        let filter = RegexFilter::new("usr/lib").unwrap().and(NotFilter::new(RegexFilter::new("debug").unwrap()));
+        assert!(filter.filter_bytes(b"./usr/lib/libnss3.so   libs/nss\n"));
+        assert!(!filter.filter_bytes(b"./usr/lib/debug/libnss3.so   debug/nss-dbg\n"));
+        assert!(!filter.filter_bytes(b"usr/bin/bash   shells/bash\n"));
+    }
+
+    #[test]
+    fn test_or_filter() {
+        let filter = RegexFilter::new("usr/lib").unwrap().or(RegexFilter::new("usr/bin").unwrap());
+        assert!(filter.filter_bytes(b"./usr/lib/libnss3.so   libs/nss\n"));
+        assert!(filter.filter_bytes(b"usr/bin/bash   shells/bash\n"));
+        assert!(!filter.filter_bytes(b"usr/share/doc/bash   shells/bash\n"));
+    }
+
+    #[test]
+    fn test_not_filter() {
+        assert!(!AcceptAllFilter::new().not().filter_bytes(b"usr/bin/bash   shells/bash\n"));
+        assert!(NotFilter::new(AcceptAllFilter::new()).not().filter_bytes(b"usr/bin/bash   shells/bash\n"));
+    }
+
+    #[test]
+    fn test_filter_short_circuit() {
+        let called = Cell::new(false);
+        let filter = OrFilter::new(AcceptAllFilter::new(), SpyFilter { called: &called });
+        assert!(filter.filter_bytes(b"usr/bin/bash   shells/bash\n"));
+        assert!(!called.get());
+
+        let filter = AndFilter::new(AcceptAllFilter::new().not(), SpyFilter { called: &called });
+        assert!(!filter.filter_bytes(b"usr/bin/bash   shells/bash\n"));
+        assert!(!called.get());
+    }
+}
@@ -1,9 +1,22 @@
 //! Parser for APT source metadata
 
 pub mod contents;
+pub mod filters;
+pub mod packages;
+pub mod release;
+
+pub use filters::{AndFilter, ExtensionFilter, FilterExt, NotFilter, OrFilter, RegexFilter, SectionFilter};
 
 pub trait Filter {
     fn filter_bytes(&self, input: &[u8]) -> bool;
+
+    /// Whether blank lines reach [`Filter::filter_bytes`]
+    ///
+    /// The iterators skip blank lines without consulting the filter unless
+    /// this returns `true`.
+    fn accept_empty(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Clone, Debug, Default)]
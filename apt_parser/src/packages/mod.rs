@@ -0,0 +1,337 @@
+//! Parser for `Packages` indices, the list of binary packages of a repository
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+
+/// Fields of a stanza as `(name, value)`, in file order
+pub type Fields = Vec<(String, String)>;
+
+/// Error for a stanza which could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StanzaError {
+    /// Input without any stanza, e.g. an empty file
+    Empty,
+    /// Line neither starting a field nor continuing one
+    MalformedLine(String),
+    /// Mandatory field missing from the stanza
+    MissingField(&'static str),
+    /// Field with a value which could not be parsed
+    InvalidField(&'static str, String),
+    /// Error reading the input, e.g. a truncated gzip stream
+    Io(std::io::ErrorKind),
+}
+
+/// Binary package listed in a `Packages` index
+///
+/// Fields without a dedicated accessor are kept in [`Package::get_field`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Package {
+    package: String,
+    version: String,
+    architecture: String,
+    depends: Vec<String>,
+    filename: String,
+    size: u64,
+    sha256: Option<String>,
+    description: Option<String>,
+    extra: HashMap<String, String>,
+}
+
+/// Iterator over the stanzas of a `Packages` index
+///
+/// A stanza which fails to parse yields an error, iteration then carries on
+/// with the next one. A read error is yielded as [`StanzaError::Io`] and
+/// ends the iteration.
+#[derive(Debug)]
+pub struct PackagesIterator<R> {
+    reader: BufReader<R>,
+    failed: bool,
+}
+
+/// Read the next stanza, `None` once the input is exhausted
+///
+/// Stanzas are separated by blank lines. A line starting with a space or a
+/// tab continues the value of the previous field, joined with a `\n`, so a
+/// ` .` line is kept as part of a description rather than ending it. On a
+/// malformed line the rest of the stanza is consumed before the error is
+/// returned, so that the next call starts on a fresh stanza. A read error is
+/// returned as [`StanzaError::Io`], dropping the partial stanza.
+///
+/// This is the deb822 parser behind [`PackagesIterator`] and
+/// [`crate::release::Release`], usable on any other control file.
+pub fn read_stanza<R: BufRead>(reader: &mut R) -> Option<Result<Fields, StanzaError>> {
+    let mut fields: Fields = Vec::new();
+    let mut error = None;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => return Some(Err(StanzaError::Io(e.kind()))),
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.is_empty() {
+            if fields.is_empty() && error.is_none() {
+                continue;
+            }
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            match fields.last_mut() {
+                Some((_, value)) => {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+                None => {
+                    error.get_or_insert_with(|| StanzaError::MalformedLine(line.to_string()));
+                }
+            }
+            continue;
+        }
+        match line.split_once(':') {
+            Some((name, value)) => fields.push((name.to_string(), value.trim().to_string())),
+            None => {
+                error.get_or_insert_with(|| StanzaError::MalformedLine(line.to_string()));
+            }
+        }
+    }
+    match error {
+        Some(error) => Some(Err(error)),
+        None if fields.is_empty() => None,
+        None => Some(Ok(fields)),
+    }
+}
+
+impl fmt::Display for StanzaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            StanzaError::Empty => write!(f, "no stanza found"),
+            StanzaError::MalformedLine(line) => write!(f, "malformed line: {}", line),
+            StanzaError::MissingField(field) => write!(f, "missing field {}", field),
+            StanzaError::InvalidField(field, value) => write!(f, "invalid value for field {}: {}", field, value),
+            StanzaError::Io(kind) => write!(f, "read error: {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for StanzaError {}
+
+impl Package {
+    /// Build a package out of the fields of a stanza
+    pub(crate) fn from_fields(fields: Fields) -> Result<Self, StanzaError> {
+        let mut fields: HashMap<String, String> = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value))
+            .collect();
+        let mut take = |field: &'static str| fields.remove(&field.to_ascii_lowercase());
+        let package = take("Package").ok_or(StanzaError::MissingField("Package"))?;
+        let version = take("Version").ok_or(StanzaError::MissingField("Version"))?;
+        let architecture = take("Architecture").ok_or(StanzaError::MissingField("Architecture"))?;
+        let filename = take("Filename").ok_or(StanzaError::MissingField("Filename"))?;
+        let size = take("Size").ok_or(StanzaError::MissingField("Size"))?;
+        let size = size.parse().map_err(|_| StanzaError::InvalidField("Size", size))?;
+        let depends = take("Depends")
+            .map(|depends| {
+                depends
+                    .split(',')
+                    .map(|depend| depend.split_whitespace().collect::<Vec<_>>().join(" "))
+                    .filter(|depend| !depend.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let sha256 = take("SHA256");
+        let description = take("Description");
+        Ok(Self {
+            package,
+            version,
+            architecture,
+            depends,
+            filename,
+            size,
+            sha256,
+            description,
+            extra: fields,
+        })
+    }
+
+    pub fn get_package(&self) -> &str {
+        &self.package
+    }
+
+    pub fn get_version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn get_architecture(&self) -> &str {
+        &self.architecture
+    }
+
+    /// Entries of the `Depends` field, e.g. `libc6 (>= 2.34)`
+    pub fn get_depends(&self) -> &[String] {
+        &self.depends
+    }
+
+    /// Path of the package relative to the root of the repository
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn get_sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+
+    /// Raw `Description` field, continuation lines included
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Extended description following the synopsis line of `Description`
+    ///
+    /// The leading space of each continuation line is removed and lines
+    /// holding a single `.` become blank lines, as in `deb-control(5)`.
+    pub fn get_long_description(&self) -> Option<String> {
+        let (_, long) = self.description.as_deref()?.split_once('\n')?;
+        let lines: Vec<&str> = long
+            .lines()
+            .map(|line| {
+                let line = line.strip_prefix([' ', '\t']).unwrap_or(line);
+                if line == "." {
+                    ""
+                } else {
+                    line
+                }
+            })
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    /// Value of a field without a dedicated accessor, e.g. `Section`
+    ///
+    /// Field names are case-insensitive.
+    pub fn get_field(&self, name: &str) -> Option<&str> {
+        self.extra.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+}
+
+impl<R: Read> Iterator for PackagesIterator<R> {
+    type Item = Result<Package, StanzaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = read_stanza(&mut self.reader)?;
+        self.failed = matches!(result, Err(StanzaError::Io(_)));
+        Some(result.and_then(Package::from_fields))
+    }
+}
+
+impl<R: Read> PackagesIterator<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            reader: BufReader::new(read),
+            failed: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PackagesIterator, StanzaError};
+
+    use std::env;
+    use std::fs;
+    use std::io::{self, Read};
+
+    fn dummy() -> fs::File {
+        fs::File::open(format!("{}/tests/Packages-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap()
+    }
+
+    #[test]
+    fn test_packages_dummy() {
+        let packages: Vec<_> = PackagesIterator::new(dummy()).map(Result::unwrap).collect();
+        let names: Vec<&str> = packages.iter().map(|package| package.get_package()).collect();
+        assert_eq!(names, ["bash", "libnss3", "tzdata"]);
+        let nss = &packages[1];
+        assert_eq!(nss.get_version(), "2:3.87.1-1");
+        assert_eq!(nss.get_architecture(), "amd64");
+        assert_eq!(nss.get_depends(), ["libc6 (>= 2.34)", "libnspr4 (>= 2:4.34)", "libsqlite3-0 (>= 3.5.9)"]);
+        assert_eq!(nss.get_filename(), "pool/main/n/nss/libnss3_3.87.1-1_amd64.deb");
+        assert_eq!(nss.get_size(), 1339956);
+        assert_eq!(nss.get_sha256(), Some("6f2b6c1e6a0c3e9d7f8a1b2c3d4e5f60718293a4b5c6d7e8f9a0b1c2d3e4f5a6"));
+        assert_eq!(nss.get_description(), Some("Network Security Service libraries"));
+        assert_eq!(nss.get_field("Source"), Some("nss"));
+        assert_eq!(nss.get_field("multi-arch"), Some("same"));
+        assert_eq!(nss.get_field("Package"), None);
+        assert!(packages[2].get_depends().is_empty());
+    }
+
+    #[test]
+    fn test_packages_errors() {
+        let input = "Package: foo\nVersion: 1.0\nArchitecture: all\nFilename: foo.deb\n\n\
+            Package: bar\nnot a field\nVersion: 1.0\n\n\
+            Package: baz\nVersion: 1.0\nArchitecture: all\nFilename: baz.deb\nSize: big\n\n\
+            Package: qux\nVersion: 1.0\nArchitecture: all\nFilename: qux.deb\nSize: 1\n";
+        let result: Vec<_> = PackagesIterator::new(input.as_bytes()).collect();
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[0], Err(StanzaError::MissingField("Size")));
+        assert_eq!(result[1], Err(StanzaError::MalformedLine("not a field".to_string())));
+        assert_eq!(result[2], Err(StanzaError::InvalidField("Size", "big".to_string())));
+        assert_eq!(result[3].as_ref().unwrap().get_package(), "qux");
+    }
+
+    #[test]
+    fn test_packages_io_error() {
+        struct Truncated;
+
+        impl Read for Truncated {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated"))
+            }
+        }
+
+        let input = "Package: foo\nVersion: 1.0\nArchitecture: all\nFilename: foo.deb\nSize: 1\n\nPackage: bar\nVers";
+        let mut parser = PackagesIterator::new(input.as_bytes().chain(Truncated));
+        assert_eq!(parser.next().unwrap().unwrap().get_package(), "foo");
+        assert_eq!(parser.next(), Some(Err(StanzaError::Io(io::ErrorKind::UnexpectedEof))));
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn test_packages_folded_fields() {
+        let input = "Package: foo\nVersion: 1.0\nArchitecture: all\n\
+            Depends: libc6 (>= 2.34),\n libbar,\n\tlibbaz | libqux\n\
+            Filename: foo.deb\nSize: 1\n\
+            Description: synopsis of foo\n \
+            First paragraph of the\n description.\n .\n \
+            Second paragraph.\n  Indented line.\n\
+            Section: misc\n\n\
+            Package: bar\nVersion: 1.0\nArchitecture: all\nFilename: bar.deb\nSize: 1\nDescription: bar\n";
+        let packages: Vec<_> = PackagesIterator::new(input.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(packages.len(), 2);
+        let foo = &packages[0];
+        assert_eq!(foo.get_depends(), ["libc6 (>= 2.34)", "libbar", "libbaz | libqux"]);
+        assert_eq!(foo.get_field("Section"), Some("misc"));
+        assert_eq!(
+            foo.get_description(),
+            Some("synopsis of foo\n First paragraph of the\n description.\n .\n Second paragraph.\n  Indented line.")
+        );
+        assert_eq!(
+            foo.get_long_description().as_deref(),
+            Some("First paragraph of the\ndescription.\n\nSecond paragraph.\n Indented line.")
+        );
+        assert_eq!(packages[1].get_package(), "bar");
+        assert_eq!(packages[1].get_long_description(), None);
+    }
+}
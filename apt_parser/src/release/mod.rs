@@ -0,0 +1,192 @@
+//! Parser for `Release` files, the signed index of a repository suite
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use std::collections::HashMap;
+use std::io::{BufReader, Read};
+
+use crate::packages::{read_stanza, StanzaError};
+
+/// Checksum algorithms listed in a `Release` file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HashKind {
+    Md5Sum,
+    Sha1,
+    Sha256,
+}
+
+/// Index file listed in a checksum section of a `Release` file
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReleaseFile {
+    path: String,
+    size: u64,
+    hash: String,
+}
+
+/// Parsed `Release` file
+///
+/// Header fields such as `Suite` or `Date` are available through
+/// [`Release::get_field`], the checksum sections through
+/// [`Release::get_files`] and [`Release::get_file`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Release {
+    fields: HashMap<String, String>,
+    files: HashMap<HashKind, Vec<ReleaseFile>>,
+}
+
+impl HashKind {
+    /// Name of the section listing this checksum
+    pub fn field_name(&self) -> &'static str {
+        match self {
+            HashKind::Md5Sum => "MD5Sum",
+            HashKind::Sha1 => "SHA1",
+            HashKind::Sha256 => "SHA256",
+        }
+    }
+}
+
+impl ReleaseFile {
+    /// Parse a ` <hash> <size> <path>` line of a checksum section
+    fn parse(kind: HashKind, line: &str) -> Result<Self, StanzaError> {
+        let invalid = || StanzaError::InvalidField(kind.field_name(), line.to_string());
+        let mut columns = line.split_whitespace();
+        let (Some(hash), Some(size), Some(path), None) = (columns.next(), columns.next(), columns.next(), columns.next()) else {
+            return Err(invalid());
+        };
+        Ok(Self {
+            path: path.to_string(),
+            size: size.parse().map_err(|_| invalid())?,
+            hash: hash.to_string(),
+        })
+    }
+
+    /// Path relative to the directory of the `Release` file
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    /// Hex encoded checksum
+    pub fn get_hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+impl Release {
+    /// Parse a `Release` file, `InRelease` files must be stripped of their
+    /// signature first
+    ///
+    /// An input without any stanza is reported as [`StanzaError::Empty`].
+    pub fn parse<R: Read>(read: R) -> Result<Self, StanzaError> {
+        let mut reader = BufReader::new(read);
+        let fields = read_stanza(&mut reader).ok_or(StanzaError::Empty)??;
+        let mut ret = Self {
+            fields: HashMap::new(),
+            files: HashMap::new(),
+        };
+        for (name, value) in fields {
+            let kind = [HashKind::Md5Sum, HashKind::Sha1, HashKind::Sha256]
+                .into_iter()
+                .find(|kind| kind.field_name().eq_ignore_ascii_case(&name));
+            match kind {
+                Some(kind) => {
+                    let files = value
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(|line| ReleaseFile::parse(kind, line))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    ret.files.insert(kind, files);
+                }
+                None => {
+                    ret.fields.insert(name.to_ascii_lowercase(), value);
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Value of a header field, e.g. `Codename`
+    ///
+    /// Field names are case-insensitive.
+    pub fn get_field(&self, name: &str) -> Option<&str> {
+        self.fields.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    /// Files listed with a `kind` checksum, empty if the section is missing
+    pub fn get_files(&self, kind: HashKind) -> &[ReleaseFile] {
+        self.files.get(&kind).map_or(&[], Vec::as_slice)
+    }
+
+    /// Expected size and `kind` checksum of `path`, e.g. `main/Contents-amd64.gz`
+    pub fn get_file(&self, kind: HashKind, path: &str) -> Option<&ReleaseFile> {
+        self.get_files(kind).iter().find(|file| file.path == path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HashKind, Release};
+    use crate::packages::StanzaError;
+
+    use std::env;
+    use std::fs;
+    use std::io::{self, Read};
+
+    fn dummy() -> Release {
+        let file = fs::File::open(format!("{}/tests/Release-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap();
+        Release::parse(file).unwrap()
+    }
+
+    #[test]
+    fn test_release_fields() {
+        let release = dummy();
+        assert_eq!(release.get_field("Codename"), Some("bookworm"));
+        assert_eq!(release.get_field("suite"), Some("stable"));
+        assert_eq!(release.get_field("Date"), Some("Sat, 10 Feb 2024 10:07:46 UTC"));
+        assert_eq!(release.get_field("SHA256"), None);
+    }
+
+    #[test]
+    fn test_release_files() {
+        let release = dummy();
+        assert_eq!(release.get_files(HashKind::Md5Sum).len(), 4);
+        assert_eq!(release.get_files(HashKind::Sha256).len(), 4);
+        assert!(release.get_files(HashKind::Sha1).is_empty());
+        let contents = release.get_file(HashKind::Sha256, "main/Contents-amd64.gz").unwrap();
+        assert_eq!(contents.get_size(), 56456621);
+        assert_eq!(contents.get_hash(), "b1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2");
+        let contents = release.get_file(HashKind::Md5Sum, "main/Contents-amd64.gz").unwrap();
+        assert_eq!(contents.get_hash(), "f5a0b1e7e0a8f8b3a5f1d0e6f1d0e6f1");
+        assert_eq!(release.get_file(HashKind::Sha256, "main/Contents-arm64.gz"), None);
+    }
+
+    #[test]
+    fn test_release_invalid() {
+        let input = "Suite: stable\nSHA256:\n 0123 notasize main/Contents-amd64.gz\n";
+        let err = Release::parse(input.as_bytes()).unwrap_err();
+        assert_eq!(err, StanzaError::InvalidField("SHA256", " 0123 notasize main/Contents-amd64.gz".to_string()));
+        assert_eq!(Release::parse(&b""[..]), Err(StanzaError::Empty));
+        assert_eq!(Release::parse(&b"\n\n"[..]), Err(StanzaError::Empty));
+    }
+
+    #[test]
+    fn test_release_io_error() {
+        struct Truncated;
+
+        impl Read for Truncated {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated"))
+            }
+        }
+
+        let input = (&b"Suite: stable\nSHA256:\n"[..]).chain(Truncated);
+        assert_eq!(Release::parse(input), Err(StanzaError::Io(io::ErrorKind::UnexpectedEof)));
+    }
+}
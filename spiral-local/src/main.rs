@@ -1,12 +1,18 @@
 use anyhow::Error;
 use backtrace::Backtrace as ExternalBacktrace;
 use clap::{Args, Subcommand, Parser};
-use log::{debug, info, warn};
+use flate2::read::GzDecoder;
+use log::debug;
+use strum::VariantNames;
 
 use spiral::{EmptyPackage, Architecture};
+use spiral::metadata::LookupTable;
+use spiral::metadata::contents::ContentsParser;
+use spiral::package::read_control;
 
 use std::env;
 use std::fs;
+use std::io::Read;
 use std::panic;
 use std::path::PathBuf;
 
@@ -26,6 +32,14 @@ struct GenerateOpts {
     package_version: String,
     #[clap(short = 'd', long = "depend", help = "Dependencies of the package")]
     dependencies: Vec<String>,
+    #[clap(
+        short = 'a',
+        long = "architecture",
+        help = "Architecture of the package",
+        default_value = "all",
+        value_parser = parse_architecture
+    )]
+    architecture: Architecture,
     #[clap(
         short = 'o',
         long = "output",
@@ -34,6 +48,26 @@ struct GenerateOpts {
     output: Option<PathBuf>,
 }
 
+#[derive(Args, Debug)]
+struct InspectOpts {
+    #[clap(help = "Path of the package to inspect")]
+    package: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct TranslateOpts {
+    #[clap(help = "Path of the Contents file, gzip-compressed if it ends with .gz")]
+    contents: PathBuf,
+    #[clap(
+        short = 'f',
+        long = "filter",
+        help = "Only keep libraries whose translated, dev or base name matches the regular expression"
+    )]
+    filter: Option<String>,
+    #[clap(short = 'o', long = "output", help = "Output path of the lookup table")]
+    output: Option<PathBuf>,
+}
+
 #[derive(Args, Debug)]
 struct InstallOpts {
     packages: Vec<PathBuf>,
@@ -42,6 +76,10 @@ struct InstallOpts {
 #[derive(Subcommand, Debug)]
 enum Commands {
     Generate(GenerateOpts),
+    /// Print the control file of a package
+    Inspect(InspectOpts),
+    /// Build a lookup table of shared libraries from a Contents file
+    Translate(TranslateOpts),
 }
 
 #[derive(Parser, Debug)]
@@ -51,9 +89,20 @@ struct Opts {
     commands: Commands,
 }
 
+/// Parse an architecture name, listing the valid ones on failure
+fn parse_architecture(s: &str) -> Result<Architecture, String> {
+    s.parse().map_err(|_| {
+        format!(
+            "unknown architecture {:?}, expected one of: {}",
+            s,
+            Architecture::VARIANTS.join(", ")
+        )
+    })
+}
+
 /// Set panic hook with repository information
 fn setup_panic_hook() {
-    panic::set_hook(Box::new(move |panic_info: &panic::PanicInfo| {
+    panic::set_hook(Box::new(move |panic_info: &panic::PanicHookInfo| {
         if let Some(info) = panic_info.payload().downcast_ref::<&str>() {
             println!("Panic occurred: {:?}", info);
         } else {
@@ -78,7 +127,7 @@ fn handle_generate(opts: GenerateOpts) -> Result<(), Error> {
     let package = EmptyPackage::new(
         opts.package_name.as_str(),
         opts.package_version.as_str(),
-        Architecture::ALL,
+        opts.architecture,
         "Spiral Admin <admin@spiral.v2bv.net>",
         "Spiral package",
         opts.dependencies,
@@ -86,15 +135,46 @@ fn handle_generate(opts: GenerateOpts) -> Result<(), Error> {
     let output_path = if let Some(output) = opts.output {
         output
     } else {
+        let architecture = match opts.architecture {
+            Architecture::ALL => String::from("noarch"),
+            architecture => architecture.to_string(),
+        };
         PathBuf::from(format!(
-            "./{}-{}-noarch.package",
-            opts.package_name, opts.package_version
+            "./{}-{}-{}.package",
+            opts.package_name, opts.package_version, architecture
         ))
     };
     fs::write(output_path, package.build()?)?;
     Ok(())
 }
 
+fn handle_inspect(opts: InspectOpts) -> Result<(), Error> {
+    let package = fs::read(opts.package)?;
+    print!("{}", read_control(&package)?);
+    Ok(())
+}
+
+fn handle_translate(opts: TranslateOpts) -> Result<(), Error> {
+    let file = fs::File::open(&opts.contents)?;
+    let mut read: Box<dyn Read> = if opts.contents.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut builder = ContentsParser::builder();
+    if let Some(filter) = opts.filter {
+        builder = builder.regex(filter);
+    }
+    let table: LookupTable = builder.build()?.parse_without_hash(&mut read)?.into_iter().collect();
+    let json = serde_json::to_string_pretty(&table)?;
+    if let Some(output) = opts.output {
+        fs::write(output, json)?;
+    } else {
+        println!("{}", json);
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Error> {
     // Setup panic hook
     setup_panic_hook();
@@ -113,5 +193,72 @@ fn main() -> Result<(), Error> {
 
     match opts.commands {
         Commands::Generate(o) => handle_generate(o),
+        Commands::Inspect(o) => handle_inspect(o),
+        Commands::Translate(o) => handle_translate(o),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{handle_generate, handle_translate, Commands, Opts};
+
+    use clap::Parser;
+    use spiral::metadata::LookupTable;
+    use spiral::package::read_control;
+
+    use std::env;
+    use std::fs;
+
+    fn parse(args: &[&str]) -> Result<Opts, clap::Error> {
+        Opts::try_parse_from([&["spiral-local"], args].concat())
+    }
+
+    #[test]
+    fn generate_architecture() {
+        let output = env::temp_dir().join("spiral-local-riscv64.deb");
+        let output = output.to_str().unwrap();
+        let opts = parse(&["generate", "-n", "test", "-p", "1.0", "--architecture", "riscv64", "-o", output]).unwrap();
+        let Commands::Generate(opts) = opts.commands else {
+            panic!("Expected generate command");
+        };
+        handle_generate(opts).unwrap();
+        let control = read_control(&fs::read(output).unwrap()).unwrap();
+        assert!(control.contains("\nArchitecture: riscv64\n"));
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn generate_unknown_architecture() {
+        let err = parse(&["generate", "-n", "test", "-p", "1.0", "-a", "vax"]).unwrap_err();
+        assert!(err.to_string().contains("expected one of: amd64, arm64"));
+    }
+
+    #[test]
+    fn translate_dummy() {
+        let contents = format!("{}/../apt_parser/tests/Contents-amd64-dummy", env!("CARGO_MANIFEST_DIR"));
+        let output = env::temp_dir().join("spiral-local-translate.json");
+        let output = output.to_str().unwrap();
+        let opts = parse(&["translate", &contents, "--filter", "libiso9660|libnss3", "-o", output]).unwrap();
+        let Commands::Translate(opts) = opts.commands else {
+            panic!("Expected translate command");
+        };
+        handle_translate(opts).unwrap();
+        let table: LookupTable = serde_json::from_str(&fs::read_to_string(output).unwrap()).unwrap();
+        fs::remove_file(output).unwrap();
+        assert_eq!(table.get("libnss3"), Some("nss"));
+        assert_eq!(table.get("libiso9660-11"), Some("libcdio"));
+        assert_eq!(table.get("libiso9660-dev"), Some("libcdio"));
+        assert_eq!(table.get("libnotify4"), None);
+
+        let opts = parse(&["translate", &contents, "--filter", "^libnss3$", "-o", output]).unwrap();
+        let Commands::Translate(opts) = opts.commands else {
+            panic!("Expected translate command");
+        };
+        handle_translate(opts).unwrap();
+        let table: LookupTable = serde_json::from_str(&fs::read_to_string(output).unwrap()).unwrap();
+        fs::remove_file(output).unwrap();
+        assert_eq!(table.get("libnss3"), Some("nss"));
+        assert_eq!(table.get("libnss3-dev"), Some("nss"));
+        assert_eq!(table.len(), 2);
     }
 }
@@ -2,4 +2,4 @@ pub mod metadata;
 pub mod package;
 pub mod translate;
 
-pub use package::{EmptyPackage, Architecture};
+pub use package::{EmptyPackage, Alternative, Architecture, CompressionKind, Control, ControlParseError, Dependency, FileOwner, PackageError, Relation, TarPrefix};
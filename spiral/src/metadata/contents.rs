@@ -0,0 +1,481 @@
+//! Shared libraries listed in a Contents file
+
+use apt_parser::contents::{take_line_so, trim_line_ending, File};
+use regex::{Regex, RegexBuilder, RegexSet};
+use sha2::Digest;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::Arc;
+
+use crate::package::{Architecture, EmptyPackage};
+use crate::translate::Lib;
+
+/// Callback receiving the number of lines processed so far
+pub type ProgressFn = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// Parser collecting the shared libraries of a Contents file
+#[derive(Clone, Default)]
+pub struct ContentsParser {
+    regex: Option<Regex>,
+    regex_set: Option<RegexSet>,
+    sover_components: Option<usize>,
+    keep_duplicates: bool,
+    progress: Option<(u64, ProgressFn)>,
+}
+
+/// Builder for a [`ContentsParser`] with several options set
+#[derive(Clone, Default)]
+pub struct ContentsParserBuilder {
+    pattern: Option<String>,
+    case_insensitive: bool,
+    sover_components: Option<usize>,
+    keep_duplicates: bool,
+    progress: Option<(u64, ProgressFn)>,
+}
+
+impl fmt::Debug for ContentsParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContentsParser")
+            .field("regex", &self.regex)
+            .field("regex_set", &self.regex_set)
+            .field("sover_components", &self.sover_components)
+            .field("keep_duplicates", &self.keep_duplicates)
+            .field("progress_interval", &self.progress.as_ref().map(|(interval, _)| interval))
+            .finish()
+    }
+}
+
+impl fmt::Debug for ContentsParserBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContentsParserBuilder")
+            .field("pattern", &self.pattern)
+            .field("case_insensitive", &self.case_insensitive)
+            .field("sover_components", &self.sover_components)
+            .field("keep_duplicates", &self.keep_duplicates)
+            .field("progress_interval", &self.progress.as_ref().map(|(interval, _)| interval))
+            .finish()
+    }
+}
+
+impl ContentsParserBuilder {
+    pub fn new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
+    /// Only keep libraries whose translated, dev or base name matches `pattern`
+    pub fn regex<S: AsRef<str>>(mut self, pattern: S) -> Self {
+        self.pattern = Some(pattern.as_ref().to_string());
+        self
+    }
+
+    /// Match the regex ignoring case, off by default
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// See [`Lib::with_sover_components`]
+    pub fn sover_components(mut self, sover_components: usize) -> Self {
+        self.sover_components = Some(sover_components);
+        self
+    }
+
+    /// See [`ContentsParser::with_dedup`]
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.keep_duplicates = !dedup;
+        self
+    }
+
+    /// See [`ContentsParser::with_progress`]
+    pub fn progress<F: Fn(u64) + Send + Sync + 'static>(mut self, interval: u64, progress: F) -> Self {
+        self.progress = Some((interval, Arc::new(progress)));
+        self
+    }
+
+    /// Build the parser, failing if the regex does not compile
+    pub fn build(self) -> Result<ContentsParser, regex::Error> {
+        let regex = match self.pattern {
+            Some(pattern) => Some(RegexBuilder::new(&pattern).case_insensitive(self.case_insensitive).build()?),
+            None => None,
+        };
+        Ok(ContentsParser {
+            regex,
+            regex_set: None,
+            sover_components: self.sover_components,
+            keep_duplicates: self.keep_duplicates,
+            progress: self.progress,
+        })
+    }
+}
+
+impl ContentsParser {
+    pub fn new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
+    /// Only keep libraries whose translated, dev or base name matches `regex`
+    pub fn new_with_regex(regex: Regex) -> Self {
+        Self {
+            regex: Some(regex),
+            ..Default::default()
+        }
+    }
+
+    /// Only keep libraries whose translated, dev or base name matches any
+    /// regex of `regex_set`
+    ///
+    /// Cheaper than a parser per pattern for a large allow-list, the set is
+    /// tested once per library.
+    pub fn new_with_regex_set(regex_set: RegexSet) -> Self {
+        Self {
+            regex_set: Some(regex_set),
+            ..Default::default()
+        }
+    }
+
+    /// Same as [`ContentsParser::new_with_regex`], ignoring case
+    pub fn new_case_insensitive(pattern: &str) -> Result<Self, regex::Error> {
+        ContentsParserBuilder::new().regex(pattern).case_insensitive(true).build()
+    }
+
+    pub fn builder() -> ContentsParserBuilder {
+        ContentsParserBuilder::new()
+    }
+
+    /// Call `progress` every `interval` lines with the number of lines read
+    ///
+    /// The callback is shared by clones of the parser, hence the `Send` and
+    /// `Sync` bounds; use atomics or a mutex to keep state in it. Disabled by
+    /// default, an interval of 0 disables it too.
+    pub fn with_progress<F: Fn(u64) + Send + Sync + 'static>(mut self, interval: u64, progress: F) -> Self {
+        self.progress = Some((interval, Arc::new(progress)));
+        self
+    }
+
+    /// Collapse libraries appearing more than once, on by default
+    ///
+    /// When off, every matching occurrence is returned, e.g. the same soname
+    /// shipped by several packages.
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.keep_duplicates = !dedup;
+        self
+    }
+
+    fn is_match(&self, lib: &Lib) -> bool {
+        let regex = match &self.regex {
+            Some(regex) => lib.matches(regex),
+            None => true,
+        };
+        let regex_set = match &self.regex_set {
+            Some(regex_set) => lib.matches_any(regex_set),
+            None => true,
+        };
+        regex && regex_set
+    }
+
+    /// Parse the libraries out of `read`, feeding every line to `hasher`
+    ///
+    /// Unless disabled with [`ContentsParser::with_dedup`], libraries
+    /// appearing more than once are collapsed into the one with the highest
+    /// sover. The result is sorted by library name, duplicates staying in
+    /// file order. A read error, e.g. a truncated gzip stream, is returned
+    /// instead of the libraries read before it.
+    pub fn parse<R: Read, D: Digest>(&self, read: &mut R, hasher: &mut D) -> io::Result<Vec<Lib>> {
+        self.parse_lines(read, |line| hasher.update(line))
+    }
+
+    /// Same as [`ContentsParser::parse`], for callers which do not need the
+    /// hash of the input
+    pub fn parse_without_hash<R: Read>(&self, read: &mut R) -> io::Result<Vec<Lib>> {
+        self.parse_lines(read, |_| {})
+    }
+
+    fn parse_lines<R: Read, F: FnMut(&[u8])>(&self, read: &mut R, mut on_line: F) -> io::Result<Vec<Lib>> {
+        let mut reader = BufReader::new(read);
+        let mut ret: HashMap<String, Lib> = HashMap::new();
+        let mut all = Vec::new();
+        let mut buf = Vec::new();
+        let mut lines = 0;
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            on_line(&buf);
+            lines += 1;
+            if let Some((interval, progress)) = &self.progress {
+                if *interval > 0 && lines % interval == 0 {
+                    progress(lines);
+                }
+            }
+            let Ok((_, Some(entry))) = take_line_so(trim_line_ending(&buf)) else {
+                continue;
+            };
+            let (File::SharedLibrary(so), Some(package)) = (entry.get_path().get_file(), entry.get_packages().first()) else {
+                continue;
+            };
+            let mut lib = Lib::from(so).with_package_name(package.get_name());
+            if let Some(sover_components) = self.sover_components {
+                lib = lib.with_sover_components(sover_components);
+            }
+            if !self.is_match(&lib) {
+                continue;
+            }
+            if self.keep_duplicates {
+                all.push(lib);
+                continue;
+            }
+            match ret.get(lib.get_lib_name()) {
+                Some(prev) if prev.get_sover() >= lib.get_sover() => {}
+                _ => {
+                    ret.insert(lib.get_lib_name().to_string(), lib);
+                }
+            }
+        }
+        let mut ret: Vec<Lib> = if self.keep_duplicates {
+            all
+        } else {
+            ret.into_values().collect()
+        };
+        ret.sort_by(|a, b| a.get_lib_name().cmp(b.get_lib_name()));
+        Ok(ret)
+    }
+}
+
+/// Build one alias package per library found by `parser` in `read`
+///
+/// Each package is named after the translated library name, provides the
+/// dev name, and depends on the package shipping the library. Packages are
+/// sorted by name. Read errors are returned, see [`ContentsParser::parse`].
+pub fn build_provides_from_contents<R: Read>(
+    parser: &ContentsParser,
+    read: &mut R,
+    version: &str,
+    maintainer: &str,
+) -> io::Result<Vec<EmptyPackage>> {
+    let mut libs = parser.parse_without_hash(read)?;
+    libs.sort_by_key(|lib| lib.get_translated_lib_name());
+    Ok(libs.into_iter()
+        .map(|lib| {
+            let name = lib.get_translated_lib_name();
+            let mut package = EmptyPackage::new(
+                name.as_str(),
+                version,
+                Architecture::ALL,
+                maintainer,
+                &format!("Alias of {} for {}", lib.get_package_name(), name),
+                vec![lib.get_package_name().to_string()],
+            );
+            package.set_provides(vec![lib.get_translated_dev_name()]);
+            package
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{build_provides_from_contents, ContentsParser, ContentsParserBuilder};
+    use crate::package::read_control;
+
+    use regex::{Regex, RegexSet};
+    use sha2::{Digest, Sha256};
+
+    use std::env;
+    use std::fs;
+    use std::io::{self, Read};
+    use std::sync::{Arc, Mutex};
+
+    fn dummy() -> Vec<u8> {
+        fs::read(format!("{}/../apt_parser/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap())).unwrap()
+    }
+
+    #[test]
+    fn parse_dummy() {
+        let contents = dummy();
+        let mut hasher = Sha256::new();
+        let mut libs = ContentsParser::new().parse(&mut &contents[..], &mut hasher).unwrap();
+        libs.sort_by(|a, b| a.get_lib_name().cmp(b.get_lib_name()));
+        let names: Vec<(String, &str)> = libs
+            .iter()
+            .map(|lib| (lib.get_translated_lib_name(), lib.get_package_name()))
+            .collect();
+        assert_eq!(names, vec![
+            ("libadwaitaqt1".to_string(), "adwaita-qt"),
+            ("libiso9660-11".to_string(), "libcdio"),
+            ("libiso9660++0".to_string(), "libcdio"),
+            ("libnotify4".to_string(), "libnotify"),
+            ("libnss-sss2".to_string(), "sssd"),
+            ("libnss3".to_string(), "nss"),
+            ("libssl1".to_string(), "openssl"),
+            ("libxtst6".to_string(), "x11-lib"),
+        ]);
+        assert_eq!(hasher.finalize()[..], Sha256::digest(&contents)[..]);
+    }
+
+    #[test]
+    fn build_provides_dummy() {
+        let contents = dummy();
+        let packages = build_provides_from_contents(
+            &ContentsParser::new(),
+            &mut &contents[..],
+            "1.0",
+            "Spiral Admin <admin@spiral.v2bv.net>",
+        ).unwrap();
+        assert_eq!(packages.len(), 8);
+        let control = packages
+            .into_iter()
+            .map(|package| read_control(&package.build().unwrap()).unwrap())
+            .find(|control| control.starts_with("Package: libnss3\n"))
+            .unwrap();
+        assert!(control.contains("\nDepends: nss\n"));
+        assert!(control.contains("\nProvides: libnss3-dev\n"));
+    }
+
+    #[test]
+    fn parse_preserves_sover() {
+        let contents = "./usr/lib/libnuma.so.1.1.4   admin/numactl\n";
+        let libs = ContentsParser::new().parse_without_hash(&mut contents.as_bytes()).unwrap();
+        assert_eq!(libs.len(), 1);
+        assert_eq!(libs[0].get_sover(), &[1, 1, 4]);
+        assert_eq!(libs[0].get_lib_name(), "libnuma");
+        assert_eq!(libs[0].get_package_name(), "numactl");
+    }
+
+    #[test]
+    fn parse_dedup_highest_sover() {
+        let lines = [
+            "usr/lib/libfoo.so.1   libs/foo\n",
+            "usr/lib/libfoo.so.1.2.3   libs/foo\n",
+            "usr/lib/libfoo.so.1.2   libs/foo\n",
+        ];
+        for rotation in 0..lines.len() {
+            let mut contents = lines.to_vec();
+            contents.rotate_left(rotation);
+            let contents = contents.concat();
+            let libs = ContentsParser::new().parse_without_hash(&mut contents.as_bytes()).unwrap();
+            assert_eq!(libs.len(), 1);
+            assert_eq!(libs[0].get_sover(), &[1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn parse_with_regex() {
+        let contents = dummy();
+        let parser = ContentsParser::new_with_regex(Regex::new("^libiso9660-dev$").unwrap());
+        let libs = parser.parse_without_hash(&mut &contents[..]).unwrap();
+        assert_eq!(libs.len(), 1);
+        assert_eq!(libs[0].get_translated_lib_name(), "libiso9660-11");
+    }
+
+    #[test]
+    fn parse_with_regex_set() {
+        let contents = dummy();
+        let set = RegexSet::new(["^libnss3$", "^libssl", "^libxtst-dev$"]).unwrap();
+        let libs = ContentsParser::new_with_regex_set(set).parse_without_hash(&mut &contents[..]).unwrap();
+        let names: Vec<String> = libs.iter().map(|lib| lib.get_translated_lib_name()).collect();
+        assert_eq!(names, ["libnss3", "libssl1", "libxtst6"]);
+    }
+
+    #[test]
+    fn parse_crlf() {
+        let contents = "usr/lib/libfoo.so.1   libs/foo\r\nusr/lib/libbar.so.2   libs/bar\r\n";
+        let mut libs = ContentsParser::new().parse_without_hash(&mut contents.as_bytes()).unwrap();
+        libs.sort_by(|a, b| a.get_lib_name().cmp(b.get_lib_name()));
+        let names: Vec<(&str, &str)> = libs.iter().map(|lib| (lib.get_lib_name(), lib.get_package_name())).collect();
+        assert_eq!(names, vec![("libbar", "bar"), ("libfoo", "foo")]);
+    }
+
+    #[test]
+    fn parse_sorted() {
+        let contents = dummy();
+        let parser = ContentsParser::new();
+        let names = || -> Vec<(String, String)> {
+            parser
+                .parse_without_hash(&mut &contents[..]).unwrap()
+                .iter()
+                .map(|lib| (lib.get_lib_name().to_string(), lib.get_package_name().to_string()))
+                .collect()
+        };
+        let first = names();
+        assert_eq!(first, names());
+        assert!(first.windows(2).all(|libs| libs[0].0 <= libs[1].0));
+    }
+
+    #[test]
+    fn parse_without_dedup() {
+        let contents = "usr/lib/libfoo.so.1   libs/foo\nusr/lib32/libfoo.so.1   libs/lib32-foo\n";
+        let libs = ContentsParser::new().parse_without_hash(&mut contents.as_bytes()).unwrap();
+        assert_eq!(libs.len(), 1);
+        let libs = ContentsParser::new().with_dedup(false).parse_without_hash(&mut contents.as_bytes()).unwrap();
+        let packages: Vec<&str> = libs.iter().map(|lib| lib.get_package_name()).collect();
+        assert_eq!(packages, ["foo", "lib32-foo"]);
+        let parser = ContentsParser::builder().dedup(false).build().unwrap();
+        assert_eq!(parser.parse_without_hash(&mut contents.as_bytes()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn parse_with_builder() {
+        let contents = dummy();
+        let parser = ContentsParserBuilder::new()
+            .regex("^libiso9660-11\\.0$")
+            .sover_components(2)
+            .build()
+            .unwrap();
+        let libs = parser.parse_without_hash(&mut &contents[..]).unwrap();
+        assert_eq!(libs.len(), 1);
+        assert_eq!(libs[0].get_translated_lib_name(), "libiso9660-11.0");
+        assert!(ContentsParser::builder().regex("(").build().is_err());
+    }
+
+    #[test]
+    fn parse_case_insensitive() {
+        let contents = dummy();
+        let parser = ContentsParser::new_case_insensitive("^LIBNSS3$").unwrap();
+        let libs = parser.parse_without_hash(&mut &contents[..]).unwrap();
+        assert_eq!(libs.len(), 1);
+        assert_eq!(libs[0].get_translated_lib_name(), "libnss3");
+        assert!(ContentsParser::new_case_insensitive("(").is_err());
+    }
+
+    #[test]
+    fn parse_progress() {
+        let contents = dummy();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorder = calls.clone();
+        let parser = ContentsParser::new().with_progress(5, move |lines| recorder.lock().unwrap().push(lines));
+        parser.parse_without_hash(&mut &contents[..]).unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec![5, 10, 15]);
+    }
+
+    /// Reader failing once its data is exhausted, like a truncated gzip stream
+    struct Truncated<'a>(&'a [u8]);
+
+    impl Read for Truncated<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf)? {
+                0 => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated")),
+                read => Ok(read),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_read_error() {
+        let contents = dummy();
+        let err = ContentsParser::new().parse_without_hash(&mut Truncated(&contents)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = build_provides_from_contents(
+            &ContentsParser::new(),
+            &mut Truncated(&contents),
+            "1.0",
+            "Spiral Admin <admin@spiral.v2bv.net>",
+        ).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,9 +1,16 @@
+pub mod contents;
+pub mod packages;
+pub mod release;
+
+use log::warn;
 use serde::{Serialize, Deserialize};
 
 use std::ops::Deref;
 use std::collections::HashMap;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+use crate::translate::Lib;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct HardcodeTable {
     entries: HashMap<String, Vec<String>>,
 }
@@ -22,9 +29,35 @@ impl Deref for HardcodeTable {
 }
 
 impl HardcodeTable {
+    pub fn new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
     fn unwrap(self) -> HashMap<String, Vec<String>> {
         self.entries
     }
+
+    /// Set the translated names of a package, replacing the previous ones
+    pub fn insert<S: AsRef<str>>(&mut self, key: S, values: Vec<String>) {
+        self.entries.insert(key.as_ref().to_string(), values);
+    }
+
+    /// Union the entries of both tables
+    ///
+    /// Names of a package present in both tables are concatenated, keeping
+    /// the first occurrence of duplicates.
+    pub fn merge(&mut self, other: Self) {
+        for (key, values) in other.unwrap().into_iter() {
+            let names = self.entries.entry(key).or_default();
+            for value in values {
+                if !names.contains(&value) {
+                    names.push(value);
+                }
+            }
+        }
+    }
 }
 
 impl From<HardcodeTable> for LookupTable {
@@ -41,19 +74,33 @@ impl From<HardcodeTable> for LookupTable {
     }
 }
 
-// impl From<Vec<Lib>> for LookupTable {
-//     fn from(l: Vec<Lib>) -> Self {
-//         let mut entries = HashMap::new();
-//         for lib in l {
-//             for translated_name in vec![lib.get_translated_lib_name(), lib.get_translated_dev_name()] {
-//                 entries.insert(translated_name, lib.get_lib_name());
-//             }
-//         }
-//         Self {
-//             entries,
-//         }
-//     }
-// }
+/// Map the translated lib and dev names of each library to its package
+///
+/// When two libraries share a translated name the last one wins.
+impl FromIterator<Lib> for LookupTable {
+    fn from_iter<I: IntoIterator<Item = Lib>>(iter: I) -> Self {
+        let mut entries = HashMap::new();
+        for lib in iter {
+            for translated_name in [lib.get_translated_lib_name(), lib.get_translated_dev_name()] {
+                let package_name = lib.get_package_name().to_string();
+                if let Some(previous) = entries.insert(translated_name.clone(), package_name.clone()) {
+                    if previous != package_name {
+                        warn!("{} is provided by both {} and {}", translated_name, previous, package_name);
+                    }
+                }
+            }
+        }
+        Self {
+            entries,
+        }
+    }
+}
+
+impl From<Vec<Lib>> for LookupTable {
+    fn from(l: Vec<Lib>) -> Self {
+        l.into_iter().collect()
+    }
+}
 
 impl Deref for LookupTable {
     type Target = HashMap<String, String>;
@@ -81,4 +128,96 @@ impl LookupTable {
     pub fn append_hardcode_table(&mut self, other: HardcodeTable) {
         self.merge(Self::from(other))
     }
+
+    /// Get the canonical package name of a translated name
+    pub fn get(&self, translated_name: &str) -> Option<&str> {
+        self.entries.get(translated_name).map(|name| name.as_str())
+    }
+
+    /// Group the translated names by their canonical package name
+    ///
+    /// The translated names of each package are sorted.
+    pub fn reverse(&self) -> HashMap<String, Vec<String>> {
+        let mut ret: HashMap<String, Vec<String>> = HashMap::new();
+        for (translated_name, name) in self.entries.iter() {
+            ret.entry(name.clone()).or_default().push(translated_name.clone());
+        }
+        for names in ret.values_mut() {
+            names.sort();
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HardcodeTable, LookupTable};
+    use crate::translate::Lib;
+
+    use std::collections::HashMap;
+
+    fn hardcode_table() -> HardcodeTable {
+        HardcodeTable {
+            entries: HashMap::from([
+                ("nss".to_string(), vec!["libnss3".to_string(), "libnss3-dev".to_string()]),
+                ("libcdio".to_string(), vec!["libiso9660-11".to_string(), "libiso9660++0".to_string(), "libiso9660-dev".to_string()]),
+            ]),
+        }
+    }
+
+    #[test]
+    fn hardcode_table_merge() {
+        let mut table = hardcode_table();
+        let mut other = HardcodeTable::new();
+        other.insert("nss", vec!["libnss3-dev".to_string(), "libnss3-tools".to_string()]);
+        other.insert("openssl", vec!["libssl1".to_string()]);
+        table.merge(other);
+        assert_eq!(table.len(), 3);
+        assert_eq!(table["nss"], vec!["libnss3", "libnss3-dev", "libnss3-tools"]);
+        assert_eq!(table["openssl"], vec!["libssl1"]);
+        assert_eq!(table["libcdio"].len(), 3);
+    }
+
+    #[test]
+    fn lookup_table_get() {
+        let table = LookupTable::from(hardcode_table());
+        assert_eq!(table.get("libnss3"), Some("nss"));
+        assert_eq!(table.get("libnss3-dev"), Some("nss"));
+        assert_eq!(table.get("libiso9660++0"), Some("libcdio"));
+        assert_eq!(table.get("nss"), None);
+    }
+
+    #[test]
+    fn lookup_table_reverse() {
+        let table = LookupTable::from(hardcode_table());
+        let reversed = table.reverse();
+        assert_eq!(reversed.len(), 2);
+        assert_eq!(reversed["nss"], vec!["libnss3", "libnss3-dev"]);
+        assert_eq!(reversed["libcdio"], vec!["libiso9660++0", "libiso9660-11", "libiso9660-dev"]);
+    }
+
+    #[test]
+    fn lookup_table_from_libs() {
+        let table = LookupTable::from(vec![
+            Lib::new("libnss3", vec![]).with_package_name("nss"),
+            Lib::new("libiso9660", vec![11, 0, 0]),
+        ]);
+        assert_eq!(table.len(), 4);
+        assert_eq!(table.get("libnss3"), Some("nss"));
+        assert_eq!(table.get("libnss3-dev"), Some("nss"));
+        assert_eq!(table.get("libiso9660-11"), Some("libiso9660"));
+        assert_eq!(table.get("libiso9660-dev"), Some("libiso9660"));
+    }
+
+    #[test]
+    fn lookup_table_from_libs_collision() {
+        let table: LookupTable = [
+            Lib::new("libssl", vec![1, 1]).with_package_name("openssl"),
+            Lib::new("libssl", vec![1, 0]).with_package_name("openssl-1.0"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(table.get("libssl1"), Some("openssl-1.0"));
+        assert_eq!(table.get("libssl-dev"), Some("openssl-1.0"));
+    }
 }
@@ -0,0 +1,164 @@
+//! Writer for the `Packages` index of an APT repository
+
+use anyhow::Error;
+use apt_parser::packages::{read_stanza, Fields, StanzaError};
+use sha2::{Digest, Sha256};
+
+use std::io::Write;
+
+use crate::package::read_control;
+
+/// `Packages` index listing built packages
+#[derive(Clone, Debug, Default)]
+pub struct PackagesIndex {
+    stanzas: Vec<Fields>,
+}
+
+/// Split a control file into its fields, continuation lines are kept in the
+/// value
+fn parse_control(control: &str) -> Result<Fields, StanzaError> {
+    let fields = read_stanza(&mut control.as_bytes()).ok_or(StanzaError::Empty)??;
+    if !fields.iter().any(|(key, _)| key == "Package") {
+        return Err(StanzaError::MissingField("Package"));
+    }
+    Ok(fields)
+}
+
+impl PackagesIndex {
+    pub fn new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+
+    /// Add a package from its control file and the location of the `.deb`
+    ///
+    /// `Filename`, `Size` and `SHA256` are inserted before `Description`.
+    /// Fails on a malformed control file or one without a `Package` field.
+    pub fn add<S: AsRef<str>>(&mut self, control: &str, filename: S, size: u64, sha256: S) -> Result<(), StanzaError> {
+        let mut stanza = parse_control(control)?;
+        let position = stanza
+            .iter()
+            .position(|(key, _)| key == "Description")
+            .unwrap_or(stanza.len());
+        stanza.splice(position..position, [
+            ("Filename".to_string(), filename.as_ref().to_string()),
+            ("Size".to_string(), size.to_string()),
+            ("SHA256".to_string(), sha256.as_ref().to_string()),
+        ]);
+        self.stanzas.push(stanza);
+        Ok(())
+    }
+
+    /// Add a built package, reading its control file and hashing it
+    pub fn add_deb<S: AsRef<str>>(&mut self, filename: S, deb: &[u8]) -> Result<(), Error> {
+        let control = read_control(deb)?;
+        let sha256 = format!("{:x}", Sha256::digest(deb));
+        self.add(&control, filename.as_ref(), deb.len() as u64, &sha256)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.stanzas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stanzas.is_empty()
+    }
+
+    /// Write the index, stanzas are separated by a blank line
+    pub fn write_to<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        for (i, stanza) in self.stanzas.iter().enumerate() {
+            if i > 0 {
+                writeln!(w)?;
+            }
+            for (key, value) in stanza {
+                writeln!(w, "{}: {}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PackagesIndex;
+    use apt_parser::packages::StanzaError;
+    use crate::{Architecture, EmptyPackage};
+
+    use sha2::{Digest, Sha256};
+
+    fn build(name: &str, description: &str, depends: Vec<String>) -> Vec<u8> {
+        let mut package = EmptyPackage::new(
+            name,
+            "1.0-1",
+            Architecture::AMD64,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            description,
+            depends,
+        );
+        package.set_timestamp(1676937600);
+        package.build().unwrap()
+    }
+
+    #[test]
+    fn packages_index() {
+        let foo = build("foo", "Foo package", vec!["libc6".to_string()]);
+        let bar = build("bar", "Bar package\nLonger description.", vec![]);
+        let mut index = PackagesIndex::new();
+        index.add_deb("pool/main/f/foo/foo_1.0-1_amd64.deb", &foo).unwrap();
+        index.add_deb("pool/main/b/bar/bar_1.0-1_amd64.deb", &bar).unwrap();
+        assert_eq!(index.len(), 2);
+
+        let mut output = Vec::new();
+        index.write_to(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "Package: foo\n\
+                 Version: 1.0-1\n\
+                 Architecture: amd64\n\
+                 Maintainer: Spiral Admin <admin@spiral.v2bv.net>\n\
+                 Installed-Size: 1\n\
+                 Filename: pool/main/f/foo/foo_1.0-1_amd64.deb\n\
+                 Size: {}\n\
+                 SHA256: {:x}\n\
+                 Description: Foo package\n\
+                 Depends: libc6\n\
+                 \n\
+                 Package: bar\n\
+                 Version: 1.0-1\n\
+                 Architecture: amd64\n\
+                 Maintainer: Spiral Admin <admin@spiral.v2bv.net>\n\
+                 Installed-Size: 1\n\
+                 Filename: pool/main/b/bar/bar_1.0-1_amd64.deb\n\
+                 Size: {}\n\
+                 SHA256: {:x}\n\
+                 Description: Bar package\n \
+                 Longer description.\n",
+                foo.len(),
+                Sha256::digest(&foo),
+                bar.len(),
+                Sha256::digest(&bar),
+            )
+        );
+    }
+
+    #[test]
+    fn packages_index_malformed_control() {
+        let mut index = PackagesIndex::new();
+        assert_eq!(
+            index.add(" continued\nPackage: foo\n", "foo.deb", 1, "00"),
+            Err(StanzaError::MalformedLine(" continued".to_string()))
+        );
+        assert_eq!(
+            index.add("Package: foo\nnot a field\n", "foo.deb", 1, "00"),
+            Err(StanzaError::MalformedLine("not a field".to_string()))
+        );
+        assert_eq!(index.add("", "foo.deb", 1, "00"), Err(StanzaError::Empty));
+        assert_eq!(index.add("Version: 1.0\n", "foo.deb", 1, "00"), Err(StanzaError::MissingField("Package")));
+        assert!(index.is_empty());
+        index.add("Package: foo\nVersion: 1.0\n", "foo.deb", 1, "00").unwrap();
+        assert_eq!(index.len(), 1);
+    }
+}
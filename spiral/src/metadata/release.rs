@@ -0,0 +1,167 @@
+//! Writer for the `Release` file of an APT repository
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use std::io::Write;
+
+use crate::package::TIMESTAMP;
+use crate::Architecture;
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Index file listed in a `Release` file
+#[derive(Clone, Debug)]
+struct ReleaseFile {
+    path: String,
+    size: u64,
+    md5: String,
+    sha256: String,
+}
+
+/// `Release` file of a repository
+#[derive(Clone, Debug)]
+pub struct Release {
+    origin: String,
+    label: String,
+    suite: String,
+    codename: String,
+    architectures: Vec<Architecture>,
+    components: Vec<String>,
+    date: u64,
+    files: Vec<ReleaseFile>,
+}
+
+/// Format a UNIX timestamp as a RFC 2822 date in UTC
+fn format_date(timestamp: u64) -> String {
+    let days = timestamp / 86400;
+    let seconds = timestamp % 86400;
+    // Civil date from the number of days since 1970-01-01
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[((days + 4) % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    )
+}
+
+impl Release {
+    pub fn new<S: AsRef<str>>(origin: S, label: S, suite: S, codename: S) -> Self {
+        Self {
+            origin: String::from(origin.as_ref()),
+            label: String::from(label.as_ref()),
+            suite: String::from(suite.as_ref()),
+            codename: String::from(codename.as_ref()),
+            architectures: Vec::new(),
+            components: Vec::new(),
+            date: *TIMESTAMP,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn set_architectures(&mut self, architectures: Vec<Architecture>) {
+        self.architectures = architectures;
+    }
+
+    pub fn set_components(&mut self, components: Vec<String>) {
+        self.components = components;
+    }
+
+    /// Set the `Date` field, defaults to the same timestamp as packages
+    pub fn set_date(&mut self, date: u64) {
+        self.date = date;
+    }
+
+    /// List an index file, `path` is relative to the `Release` file
+    pub fn add_file<S: AsRef<str>>(&mut self, path: S, size: u64, md5: S, sha256: S) {
+        self.files.push(ReleaseFile {
+            path: String::from(path.as_ref()),
+            size,
+            md5: String::from(md5.as_ref()),
+            sha256: String::from(sha256.as_ref()),
+        });
+    }
+
+    /// List an index file, hashing its contents
+    pub fn add_file_contents<S: AsRef<str>>(&mut self, path: S, contents: &[u8]) {
+        let md5 = format!("{:x}", Md5::digest(contents));
+        let sha256 = format!("{:x}", Sha256::digest(contents));
+        self.add_file(path.as_ref(), contents.len() as u64, &md5, &sha256);
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        writeln!(w, "Origin: {}", self.origin)?;
+        writeln!(w, "Label: {}", self.label)?;
+        writeln!(w, "Suite: {}", self.suite)?;
+        writeln!(w, "Codename: {}", self.codename)?;
+        let architectures: Vec<String> = self.architectures.iter().map(|arch| arch.to_string()).collect();
+        writeln!(w, "Architectures: {}", architectures.join(" "))?;
+        writeln!(w, "Components: {}", self.components.join(" "))?;
+        writeln!(w, "Date: {}", format_date(self.date))?;
+        writeln!(w, "MD5Sum:")?;
+        for file in self.files.iter() {
+            writeln!(w, " {} {:>16} {}", file.md5, file.size, file.path)?;
+        }
+        writeln!(w, "SHA256:")?;
+        for file in self.files.iter() {
+            writeln!(w, " {} {:>16} {}", file.sha256, file.size, file.path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_date, Release};
+    use crate::Architecture;
+
+    #[test]
+    fn release_date() {
+        assert_eq!(format_date(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(format_date(1676937600), "Tue, 21 Feb 2023 00:00:00 +0000");
+        assert_eq!(format_date(951782400 + 86399), "Tue, 29 Feb 2000 23:59:59 +0000");
+    }
+
+    #[test]
+    fn release_write() {
+        let mut release = Release::new("Spiral", "Spiral", "stable", "spiral");
+        release.set_architectures(vec![Architecture::AMD64, Architecture::ALL]);
+        release.set_components(vec!["main".to_string()]);
+        release.set_date(1676937600);
+        release.add_file_contents("main/binary-amd64/Packages", b"");
+        release.add_file("main/binary-amd64/Packages.gz", 1234, "0123456789abcdef0123456789abcdef", "ab");
+
+        let mut output = Vec::new();
+        release.write_to(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Origin: Spiral\n\
+             Label: Spiral\n\
+             Suite: stable\n\
+             Codename: spiral\n\
+             Architectures: amd64 all\n\
+             Components: main\n\
+             Date: Tue, 21 Feb 2023 00:00:00 +0000\n\
+             MD5Sum:\n \
+             d41d8cd98f00b204e9800998ecf8427e                0 main/binary-amd64/Packages\n \
+             0123456789abcdef0123456789abcdef             1234 main/binary-amd64/Packages.gz\n\
+             SHA256:\n \
+             e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855                0 main/binary-amd64/Packages\n \
+             ab             1234 main/binary-amd64/Packages.gz\n"
+        );
+    }
+}
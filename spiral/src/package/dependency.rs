@@ -0,0 +1,197 @@
+use strum::{Display, EnumString};
+
+use std::fmt;
+
+/// Version relation of a dependency, e.g. the `>=` in `libfoo (>= 1.2)`
+#[derive(Copy, Clone, Debug, Display, PartialEq, Eq, EnumString)]
+pub enum Relation {
+    #[strum(to_string = ">=")]
+    GreaterEq,
+    #[strum(to_string = "<=")]
+    LessEq,
+    #[strum(to_string = "=")]
+    Eq,
+    #[strum(to_string = ">>")]
+    Greater,
+    #[strum(to_string = "<<")]
+    Less,
+}
+
+/// Single package of a dependency, e.g. `libfoo (>= 1.2)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alternative {
+    name: String,
+    version: Option<(Relation, String)>,
+}
+
+/// Package relationship, rendered with the Debian syntax
+///
+/// A dependency is satisfied by any of its alternatives, which are
+/// rendered separated by ` | `.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dependency {
+    alternatives: Vec<Alternative>,
+}
+
+impl Alternative {
+    /// Parse `name` or `name (relation version)`, anything else is kept
+    /// verbatim as the name
+    fn parse(alternative: &str) -> Self {
+        let versioned = alternative.split_once('(').and_then(|(name, version)| {
+            let name = name.trim();
+            let version = version.strip_suffix(')')?.trim();
+            let end = version.find(|c: char| !['<', '>', '='].contains(&c))?;
+            let relation = version[..end].parse().ok()?;
+            let version = version[end..].trim();
+            if name.is_empty() || name.contains(char::is_whitespace) || version.is_empty() {
+                return None;
+            }
+            Some(Self {
+                name: String::from(name),
+                version: Some((relation, String::from(version))),
+            })
+        });
+        versioned.unwrap_or_else(|| Self {
+            name: String::from(alternative),
+            version: None,
+        })
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_version(&self) -> Option<(Relation, &str)> {
+        self.version.as_ref().map(|(relation, version)| (*relation, version.as_str()))
+    }
+}
+
+impl Dependency {
+    /// Dependency on any version of `name`
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        Self::from(String::from(name.as_ref()))
+    }
+
+    /// Dependency on the versions of `name` satisfying `relation version`
+    pub fn versioned<S: AsRef<str>>(name: S, relation: Relation, version: S) -> Self {
+        Self {
+            alternatives: vec![Alternative {
+                name: String::from(name.as_ref()),
+                version: Some((relation, String::from(version.as_ref()))),
+            }],
+        }
+    }
+
+    /// Dependency satisfied by any of `dependencies`, e.g. `foo | bar`
+    pub fn any_of<I: IntoIterator<Item = D>, D: Into<Dependency>>(dependencies: I) -> Self {
+        Self {
+            alternatives: dependencies
+                .into_iter()
+                .flat_map(|dependency| dependency.into().alternatives)
+                .collect(),
+        }
+    }
+
+    /// Parse a dependency written with the Debian syntax, e.g. `foo (>= 1.2) | bar`
+    ///
+    /// Unlike `From<&str>`, relations and alternatives are split out.
+    /// Alternatives which are not a bare name or a versioned one, such as
+    /// `foo [amd64]`, are kept verbatim as the name.
+    pub fn parse(dependency: &str) -> Self {
+        Self {
+            alternatives: dependency
+                .split('|')
+                .map(|alternative| Alternative::parse(alternative.trim()))
+                .collect(),
+        }
+    }
+
+    pub fn get_alternatives(&self) -> &[Alternative] {
+        &self.alternatives
+    }
+}
+
+impl fmt::Display for Alternative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some((relation, version)) = &self.version {
+            write!(f, " ({} {})", relation, version)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, alternative) in self.alternatives.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "{}", alternative)?;
+        }
+        Ok(())
+    }
+}
+
+/// Bare dependency, the string is kept verbatim as the name
+///
+/// Strings already holding a relation or alternatives, such as
+/// `libfoo (>= 1.2) | libbar`, therefore render unchanged.
+impl From<&str> for Dependency {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Dependency {
+    fn from(name: String) -> Self {
+        Self {
+            alternatives: vec![Alternative { name, version: None }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Dependency, Relation};
+
+    #[test]
+    fn versioned_dependency() {
+        let dependency = Dependency::versioned("libfoo", Relation::GreaterEq, "1.2");
+        assert_eq!(dependency.to_string(), "libfoo (>= 1.2)");
+        assert_eq!(dependency.get_alternatives()[0].get_version(), Some((Relation::GreaterEq, "1.2")));
+        assert_eq!(Dependency::versioned("libfoo", Relation::Less, "2").to_string(), "libfoo (<< 2)");
+        assert_eq!("<=".parse::<Relation>(), Ok(Relation::LessEq));
+    }
+
+    #[test]
+    fn bare_dependency() {
+        let dependency = Dependency::from("libfoo");
+        assert_eq!(dependency.to_string(), "libfoo");
+        assert_eq!(dependency.get_alternatives().len(), 1);
+        assert_eq!(dependency.get_alternatives()[0].get_name(), "libfoo");
+        assert_eq!(dependency.get_alternatives()[0].get_version(), None);
+        assert_eq!(Dependency::from("libfoo".to_string()), dependency);
+    }
+
+    #[test]
+    fn alternative_dependency() {
+        let dependency = Dependency::any_of(["foo", "bar"]);
+        assert_eq!(dependency.to_string(), "foo | bar");
+        assert_eq!(dependency.get_alternatives().len(), 2);
+        let dependency = Dependency::any_of([Dependency::versioned("foo", Relation::GreaterEq, "1.2"), Dependency::new("bar")]);
+        assert_eq!(dependency.to_string(), "foo (>= 1.2) | bar");
+    }
+
+    #[test]
+    fn parse_dependency() {
+        assert_eq!(Dependency::parse("libfoo (>= 1.2)"), Dependency::versioned("libfoo", Relation::GreaterEq, "1.2"));
+        assert_eq!(Dependency::parse("libfoo(<<2)"), Dependency::versioned("libfoo", Relation::Less, "2"));
+        assert_eq!(
+            Dependency::parse("foo (= 1:1.0-1) | bar"),
+            Dependency::any_of([Dependency::versioned("foo", Relation::Eq, "1:1.0-1"), Dependency::new("bar")])
+        );
+        assert_eq!(Dependency::parse("foo [amd64]"), Dependency::new("foo [amd64]"));
+        assert_eq!(Dependency::parse("foo (~ 1.0)"), Dependency::new("foo (~ 1.0)"));
+    }
+}
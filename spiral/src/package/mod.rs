@@ -1,35 +1,63 @@
-use anyhow::Error;
-use ar::{Builder as ArBuilder, Header as ArHeader};
+mod dependency;
+
+pub use dependency::{Alternative, Dependency, Relation};
+
+use anyhow::{anyhow, Error};
+use apt_parser::packages::{read_stanza, StanzaError};
+use ar::{Archive as ArArchive, Builder as ArBuilder, Header as ArHeader};
+use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use lazy_static::lazy_static;
+use md5::{Digest, Md5};
+use regex::Regex;
 use sailfish::TemplateOnce;
-use tar::{Builder as TarBuilder, EntryType, Header as TarHeader};
-use strum::{Display, EnumString};
+use tar::{Archive as TarArchive, Builder as TarBuilder, EntryType, Header as TarHeader};
+use strum::{Display, EnumIter, EnumString, EnumVariantNames, IntoEnumIterator};
 use sailfish::runtime::{Render, RenderError, Buffer};
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
 
-use std::io::{empty, Cursor, Write};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{empty, Cursor, Read, Seek, Write};
+use std::path::Path;
 use std::string::ToString;
 
+#[cfg(feature = "std-systemtime")]
+use std::env;
 #[cfg(feature = "std-systemtime")]
 use std::time::{SystemTime, UNIX_EPOCH};
 
 const DOC_DIR: &str = "usr/share/doc";
+/// Copyright file shipped when none has been set
+const DEFAULT_COPYRIGHT: &str = "No copyright information has been provided for this package.\n";
 
 #[cfg(feature = "std-systemtime")]
 lazy_static! {
-    static ref TIMESTAMP: u64 = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    /// `SOURCE_DATE_EPOCH` if set, the current time otherwise
+    pub(crate) static ref TIMESTAMP: u64 = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        });
 }
 
 #[cfg(not(feature = "std-systemtime"))]
 lazy_static! {
-    static ref TIMESTAMP: u64 = 0;
+    pub(crate) static ref TIMESTAMP: u64 = 0;
 }
 
 lazy_static! {
+    /// Package name as defined by the Debian policy, section 5.6.1
+    static ref PACKAGE_NAME_REGEX: Regex = Regex::new(r"^[a-z0-9][a-z0-9+.-]+$").unwrap();
+    /// `Full Name <email>`
+    static ref MAINTAINER_REGEX: Regex = Regex::new(r"^[^<>\n]+ <[^<>@\s]+@[^<>\s]+>$").unwrap();
     static ref DEBIAN_BINARY: Cursor<Vec<u8>> =
         Cursor::new("2.0\n".to_string().as_bytes().to_vec());
     static ref TAR_FILE_HEADER: TarHeader = {
@@ -57,40 +85,121 @@ lazy_static! {
     };
 }
 
-fn create_tar_file_header<S: AsRef<str>>(path: S, size: usize) -> TarHeader {
+/// Store `path` in the header as is
+///
+/// Unlike `TarHeader::set_path`, which normalizes the path, this keeps a
+/// leading `./` or `/` intact. Paths longer than the name field are
+/// truncated, [`append_tar_entry`] stores them in full.
+fn set_tar_path(header: &mut TarHeader, path: &str) {
+    copy_truncated(&mut header.as_old_mut().name, path);
+}
+
+fn copy_truncated(field: &mut [u8], value: &str) {
+    let len = value.len().min(field.len());
+    field.fill(0);
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+}
+
+/// Append a GNU `././@LongLink` entry holding `name` in full
+///
+/// Readers apply it to the next entry, overriding its truncated path
+/// ([`EntryType::GNULongName`]) or link name ([`EntryType::GNULongLink`]).
+fn append_gnu_long_name<W: Write>(builder: &mut TarBuilder<W>, entry_type: EntryType, name: &str) -> std::io::Result<()> {
+    let mut header = TarHeader::new_gnu();
+    set_tar_path(&mut header, "././@LongLink");
+    header.set_mode(0o644);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(0);
+    header.set_size(name.len() as u64 + 1);
+    header.set_entry_type(entry_type);
+    header.set_cksum();
+    builder.append(&header, name.as_bytes().chain(&[0][..]))
+}
+
+/// Append `header` for `path`, preceded by a GNU long name entry when `path`
+/// does not fit in the header
+///
+/// `TarBuilder::append_data` does the same but normalizes the path.
+fn append_tar_entry<W: Write, R: Read>(
+    builder: &mut TarBuilder<W>,
+    header: &TarHeader,
+    path: &str,
+    data: R,
+) -> std::io::Result<()> {
+    if path.len() > header.as_old().name.len() {
+        append_gnu_long_name(builder, EntryType::GNULongName, path)?;
+    }
+    builder.append(header, data)
+}
+
+fn create_tar_file_header<S: AsRef<str>>(path: S, size: usize, mode: u32, mtime: u64) -> TarHeader {
     let mut ret = TAR_FILE_HEADER.clone();
-    ret.set_path(String::from(path.as_ref()))
-        .expect("Failed to set tar header path");
+    set_tar_path(&mut ret, path.as_ref());
     ret.set_size(size as u64);
+    ret.set_mode(mode);
+    ret.set_mtime(mtime);
     ret.set_cksum();
     ret
 }
 
-fn create_tar_path<S: AsRef<str>, W: Write>(path: S, builder: &mut TarBuilder<W>) {
+/// Permission bits of a file added with [`EmptyPackage::add_dir`]
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> u32 {
+    0o644
+}
+
+/// Append an entry for `path` and each of its ancestors
+///
+/// Directories already in `created` are skipped, so that sharing the set
+/// across calls emits every directory exactly once.
+fn create_tar_path<S: AsRef<str>, W: Write>(
+    path: S,
+    prefix: TarPrefix,
+    mtime: u64,
+    created: &mut HashSet<String>,
+    builder: &mut TarBuilder<W>,
+) {
     let path_segments: Vec<String> = String::from(path.as_ref())
         .split('/')
         .map(|segment| segment.to_string())
         .collect();
     for i in 0..=path_segments.len() {
-        let path = "./".to_string() + &path_segments[0..i].join("/") + "/";
+        let path = if i == 0 {
+            // The root itself only has an entry when it has a name
+            if prefix == TarPrefix::None {
+                continue;
+            }
+            prefix.to_string()
+        } else {
+            format!("{}{}/", prefix, path_segments[0..i].join("/"))
+        };
+        if !created.insert(path.clone()) {
+            continue;
+        }
         let mut path_header = TAR_DIR_HEADER.clone();
-        path_header
-            .set_path(path)
-            .expect("Failed to set tar header path");
+        set_tar_path(&mut path_header, &path);
+        path_header.set_mtime(mtime);
         path_header.set_cksum();
-        builder
-            .append(&path_header, empty())
+        append_tar_entry(builder, &path_header, &path, empty())
             .expect("Failed to append header to tar");
     }
 }
 
-fn create_ar_file_header(path: Vec<u8>, size: usize) -> ArHeader {
+fn create_ar_file_header(path: Vec<u8>, size: usize, mtime: u64) -> ArHeader {
     let mut ret = ArHeader::new(path, size as u64);
     ret.set_mode(0o100644);
+    ret.set_mtime(mtime);
     ret
 }
 
-#[derive(Copy, Clone, Debug, Display, PartialEq, Eq, EnumString)]
+#[derive(Copy, Clone, Debug, Display, PartialEq, Eq, EnumIter, EnumString, EnumVariantNames)]
 #[strum(ascii_case_insensitive)]
 pub enum Architecture {
     #[strum(to_string = "amd64", serialize = "x86_64")]
@@ -123,19 +232,169 @@ pub enum Architecture {
     ALL,
 }
 
-#[derive(Debug, TemplateOnce)]
+/// GNU triplets used in multiarch library paths
+const GNU_TRIPLETS: [(Architecture, &str); 9] = [
+    (Architecture::AMD64, "x86_64-linux-gnu"),
+    (Architecture::ARM64, "aarch64-linux-gnu"),
+    (Architecture::LOONGSON3, "mips64el-linux-gnuabi64"),
+    (Architecture::PPC64EL, "powerpc64le-linux-gnu"),
+    (Architecture::RISCV64, "riscv64-linux-gnu"),
+    (Architecture::I486, "i386-linux-gnu"),
+    (Architecture::M68K, "m68k-linux-gnu"),
+    (Architecture::POWERPC, "powerpc-linux-gnu"),
+    (Architecture::PPC64, "powerpc64-linux-gnu"),
+];
+
+impl Architecture {
+    /// Every architecture, `ALL` included, in declaration order
+    pub fn all() -> impl Iterator<Item = Architecture> {
+        Self::iter()
+    }
+
+    /// Every architecture a binary can actually be built for, i.e. all but `ALL`
+    pub fn real_architectures() -> impl Iterator<Item = Architecture> {
+        Self::iter().filter(|arch| *arch != Architecture::ALL)
+    }
+
+    /// GNU triplet of the architecture, e.g. `x86_64-linux-gnu`
+    ///
+    /// Returns `None` for `ALL` and for architectures sharing a triplet.
+    pub fn to_gnu_triplet(&self) -> Option<&'static str> {
+        GNU_TRIPLETS
+            .iter()
+            .find(|(arch, _)| arch == self)
+            .map(|(_, triplet)| *triplet)
+    }
+
+    /// Inverse of [`Architecture::to_gnu_triplet`]
+    pub fn from_gnu_triplet(s: &str) -> Option<Architecture> {
+        GNU_TRIPLETS
+            .iter()
+            .find(|(_, triplet)| *triplet == s)
+            .map(|(arch, _)| *arch)
+    }
+}
+
+/// Error for a control file which could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlParseError {
+    /// Control file without any field
+    Empty,
+    /// Line neither starting a field nor continuing one
+    MalformedLine(String),
+    /// Mandatory field missing from the control file
+    MissingField(&'static str),
+    /// Field with a value which could not be parsed
+    InvalidField(&'static str, String),
+    /// Error reading the control file
+    Io(std::io::ErrorKind),
+}
+
+/// Error for a package which dpkg would refuse
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PackageError {
+    InvalidName(String),
+    InvalidVersion(String),
+    InvalidMaintainer(String),
+    UnknownConffile(String),
+    MultilineField(String),
+}
+
+/// Compression applied to the control and data archives
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CompressionKind {
+    #[default]
+    Gzip,
+    Xz,
+    Zstd,
+    None,
+}
+
+/// Prefix of the paths inside the data archive
+#[derive(Copy, Clone, Debug, Default, Display, PartialEq, Eq)]
+pub enum TarPrefix {
+    /// `./usr/bin/foo`, as built by dpkg-deb
+    #[default]
+    #[strum(to_string = "./")]
+    Dot,
+    /// `usr/bin/foo`
+    #[strum(to_string = "")]
+    None,
+    /// `/usr/bin/foo`
+    #[strum(to_string = "/")]
+    Slash,
+}
+
+/// Fields of the `control` file of a binary package
+///
+/// Built by [`EmptyPackage`], or parsed back with [`Control::parse`] out of
+/// e.g. [`read_control`].
+#[derive(Clone, Debug, PartialEq, Eq, TemplateOnce)]
 #[template(path = "control.stpl")]
-struct Control {
+pub struct Control {
     package: String,
     version: String,
     architecture: Architecture,
     maintainer: String,
     description: String,
-    depends: Vec<String>,
+    long_description: Vec<String>,
+    depends: Vec<Dependency>,
+    pre_depends: Vec<String>,
+    conflicts: Vec<String>,
+    provides: Vec<String>,
+    replaces: Vec<String>,
+    section: Option<String>,
+    priority: Option<String>,
+    homepage: Option<String>,
+    installed_size: Option<u64>,
+    source: Option<String>,
+    built_using: Option<String>,
+    extra: BTreeMap<String, String>,
+}
+
+/// File shipped inside the data archive
+#[derive(Debug)]
+struct DataFile {
+    path: String,
+    mode: u32,
+    owner: FileOwner,
+    contents: Vec<u8>,
+    md5: String,
+}
+
+/// Owner of a file inside the data archive, `root:root` by default
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileOwner {
+    uid: u64,
+    gid: u64,
+    username: String,
+    groupname: String,
+}
+
+/// Symbolic link shipped inside the data archive
+#[derive(Debug)]
+struct DataSymlink {
+    path: String,
+    target: String,
 }
 
 #[derive(Debug)]
-pub struct EmptyPackage(Control);
+pub struct EmptyPackage {
+    control: Control,
+    files: Vec<DataFile>,
+    symlinks: Vec<DataSymlink>,
+    conffiles: Vec<String>,
+    preinst: Option<String>,
+    postinst: Option<String>,
+    prerm: Option<String>,
+    postrm: Option<String>,
+    compression: CompressionKind,
+    compression_level: Compression,
+    tar_prefix: TarPrefix,
+    timestamp: u64,
+    copyright: Option<String>,
+    include_doc_dir: bool,
+}
 
 impl Render for Architecture {
     #[inline]
@@ -144,6 +403,127 @@ impl Render for Architecture {
     }
 }
 
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageError::InvalidName(name) => write!(f, "Invalid package name: {:?}", name),
+            PackageError::InvalidVersion(version) => write!(f, "Invalid package version: {:?}", version),
+            PackageError::InvalidMaintainer(maintainer) => {
+                write!(f, "Invalid maintainer, expected `Name <email>`: {:?}", maintainer)
+            }
+            PackageError::UnknownConffile(path) => {
+                write!(f, "Conffile /{} is not a file of the package", path)
+            }
+            PackageError::MultilineField(field) => {
+                write!(f, "Field {} must not contain a newline", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PackageError {}
+
+impl fmt::Display for ControlParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlParseError::Empty => write!(f, "Empty control file"),
+            ControlParseError::MalformedLine(line) => write!(f, "Malformed control line: {:?}", line),
+            ControlParseError::MissingField(field) => write!(f, "Missing control field {}", field),
+            ControlParseError::InvalidField(field, value) => write!(f, "Invalid value for field {}: {:?}", field, value),
+            ControlParseError::Io(kind) => write!(f, "Failed to read control file: {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for ControlParseError {}
+
+impl From<StanzaError> for ControlParseError {
+    fn from(error: StanzaError) -> Self {
+        match error {
+            StanzaError::Empty => ControlParseError::Empty,
+            StanzaError::MalformedLine(line) => ControlParseError::MalformedLine(line),
+            StanzaError::MissingField(field) => ControlParseError::MissingField(field),
+            StanzaError::InvalidField(field, value) => ControlParseError::InvalidField(field, value),
+            StanzaError::Io(kind) => ControlParseError::Io(kind),
+        }
+    }
+}
+
+/// Check a version against the `[epoch:]upstream_version[-debian_revision]` grammar
+fn is_valid_version(version: &str) -> bool {
+    let (epoch, rest) = match version.split_once(':') {
+        Some((epoch, rest)) => (Some(epoch), rest),
+        None => (None, version),
+    };
+    if let Some(epoch) = epoch {
+        if epoch.is_empty() || !epoch.chars().all(|c| c.is_ascii_digit()) {
+            return false;
+        }
+    }
+    let (upstream, revision) = match rest.rsplit_once('-') {
+        Some((upstream, revision)) => (upstream, Some(revision)),
+        None => (rest, None),
+    };
+    if let Some(revision) = revision {
+        if revision.is_empty()
+            || !revision
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ['+', '.', '~'].contains(&c))
+        {
+            return false;
+        }
+    }
+    upstream.starts_with(|c: char| c.is_ascii_digit())
+        && upstream
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ['.', '+', '~', '-', ':'].contains(&c))
+}
+
+impl CompressionKind {
+    /// Extension appended to the `.tar` ar member names
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CompressionKind::Gzip => ".gz",
+            CompressionKind::Xz => ".xz",
+            CompressionKind::Zstd => ".zst",
+            CompressionKind::None => "",
+        }
+    }
+
+    /// Detect the compression of an ar member from its name, e.g. `data.tar.xz`
+    pub fn from_member_name(name: &str) -> Option<Self> {
+        [Self::Gzip, Self::Xz, Self::Zstd, Self::None]
+            .into_iter()
+            .find(|kind| name.ends_with(&format!(".tar{}", kind.extension())))
+    }
+
+    fn decompress<'a, R: Read + 'a>(&self, read: R) -> Result<Box<dyn Read + 'a>, Error> {
+        Ok(match self {
+            CompressionKind::Gzip => Box::new(GzDecoder::new(read)),
+            CompressionKind::Xz => Box::new(XzDecoder::new(read)),
+            CompressionKind::Zstd => Box::new(zstd::stream::read::Decoder::new(read)?),
+            CompressionKind::None => Box::new(read),
+        })
+    }
+
+    fn compress(&self, data: Vec<u8>, level: Compression) -> Result<Vec<u8>, Error> {
+        Ok(match self {
+            CompressionKind::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), level);
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+            CompressionKind::Xz => {
+                let mut encoder = XzEncoder::new(Vec::new(), level.level().min(9));
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+            CompressionKind::Zstd => zstd::stream::encode_all(&*data, level.level().clamp(1, 19) as i32)?,
+            CompressionKind::None => data,
+        })
+    }
+}
+
 impl Control {
     fn new<S: AsRef<str>>(
         package: S,
@@ -151,24 +531,173 @@ impl Control {
         architecture: Architecture,
         maintainer: S,
         description: S,
-        depends: Vec<String>,
+        depends: Vec<Dependency>,
     ) -> Self {
+        let (description, long_description) = Self::split_description(description.as_ref());
         Self {
             package: String::from(package.as_ref()),
             version: String::from(version.as_ref()),
             architecture,
             maintainer: String::from(maintainer.as_ref()),
-            description: String::from(description.as_ref()),
+            description,
+            long_description,
             depends,
+            pre_depends: Vec::new(),
+            conflicts: Vec::new(),
+            provides: Vec::new(),
+            replaces: Vec::new(),
+            section: None,
+            priority: None,
+            homepage: None,
+            installed_size: None,
+            source: None,
+            built_using: None,
+            extra: BTreeMap::new(),
         }
     }
 
-    fn get_name(&self) -> &str {
+    /// Parse the first stanza of a control file
+    ///
+    /// Fields are split with apt_parser's [`read_stanza`]. Continuation lines
+    /// of `Description` make up the long description, `Depends` is split with
+    /// [`Dependency::parse`]. Fields without a dedicated member are kept as is
+    /// and rendered after the others.
+    pub fn parse(text: &str) -> Result<Self, ControlParseError> {
+        let mut fields = read_stanza(&mut text.as_bytes()).ok_or(ControlParseError::Empty)??;
+        let mut take = |field: &'static str| {
+            let i = fields.iter().position(|(name, _)| name.eq_ignore_ascii_case(field))?;
+            Some(fields.remove(i).1)
+        };
+        let list = |value: Option<String>| -> Vec<String> {
+            value
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|item| item.split_whitespace().collect::<Vec<_>>().join(" "))
+                        .filter(|item| !item.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let package = take("Package").ok_or(ControlParseError::MissingField("Package"))?;
+        let version = take("Version").ok_or(ControlParseError::MissingField("Version"))?;
+        let architecture = take("Architecture").ok_or(ControlParseError::MissingField("Architecture"))?;
+        let architecture = architecture
+            .parse()
+            .map_err(|_| ControlParseError::InvalidField("Architecture", architecture))?;
+        let maintainer = take("Maintainer").ok_or(ControlParseError::MissingField("Maintainer"))?;
+        let description = take("Description").ok_or(ControlParseError::MissingField("Description"))?;
+        let mut description = description.lines();
+        let synopsis = String::from(description.next().unwrap_or_default());
+        let long_description = description
+            .map(|line| {
+                let line = line.strip_prefix([' ', '\t']).unwrap_or(line);
+                if line == "." {
+                    String::new()
+                } else {
+                    String::from(line)
+                }
+            })
+            .collect();
+        let installed_size = match take("Installed-Size") {
+            Some(size) => Some(size.parse().map_err(|_| ControlParseError::InvalidField("Installed-Size", size))?),
+            None => None,
+        };
+        Ok(Self {
+            package,
+            version,
+            architecture,
+            maintainer,
+            description: synopsis,
+            long_description,
+            depends: list(take("Depends")).iter().map(|depend| Dependency::parse(depend)).collect(),
+            pre_depends: list(take("Pre-Depends")),
+            conflicts: list(take("Conflicts")),
+            provides: list(take("Provides")),
+            replaces: list(take("Replaces")),
+            section: take("Section"),
+            priority: take("Priority"),
+            homepage: take("Homepage"),
+            installed_size,
+            source: take("Source"),
+            built_using: take("Built-Using"),
+            extra: fields.into_iter().collect(),
+        })
+    }
+
+    pub fn get_name(&self) -> &str {
         &self.package
     }
 
-    fn into_string(self) -> String {
-        self.render_once().expect("Failed to convert to string")
+    pub fn get_version(&self) -> &str {
+        &self.version
+    }
+
+    /// Value of a field without a dedicated member, e.g. `Multi-Arch`
+    ///
+    /// Field names are case-insensitive.
+    pub fn get_extra_field(&self, name: &str) -> Option<&str> {
+        self.extra
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Split a multi-line description into its synopsis and body lines
+    ///
+    /// Body lines are rendered with a leading space, blank ones as ` .`.
+    fn split_description(description: &str) -> (String, Vec<String>) {
+        let mut lines = description.trim_end().lines();
+        let synopsis = String::from(lines.next().unwrap_or_default());
+        (synopsis, lines.map(String::from).collect())
+    }
+
+    /// Name of the first single-line field containing a newline
+    fn multiline_field(&self) -> Option<&'static str> {
+        let depends: Vec<String> = self.depends.iter().map(ToString::to_string).collect();
+        let fields = [
+            ("Package", vec![&self.package]),
+            ("Source", self.source.iter().collect()),
+            ("Version", vec![&self.version]),
+            ("Maintainer", vec![&self.maintainer]),
+            ("Description", [&self.description].into_iter().chain(self.long_description.iter()).collect()),
+            ("Section", self.section.iter().collect()),
+            ("Priority", self.priority.iter().collect()),
+            ("Homepage", self.homepage.iter().collect()),
+            ("Pre-Depends", self.pre_depends.iter().collect()),
+            ("Depends", depends.iter().collect()),
+            ("Conflicts", self.conflicts.iter().collect()),
+            ("Provides", self.provides.iter().collect()),
+            ("Replaces", self.replaces.iter().collect()),
+            ("Built-Using", self.built_using.iter().collect()),
+        ];
+        let field = fields
+            .into_iter()
+            .find(|(_, values)| values.iter().any(|value| value.contains('\n')))
+            .map(|(field, _)| field);
+        field
+    }
+
+    /// Render the control file
+    pub fn into_string(self) -> Result<String, RenderError> {
+        self.render_once()
+    }
+}
+
+impl FileOwner {
+    pub fn new<S: AsRef<str>>(uid: u64, username: S, gid: u64, groupname: S) -> Self {
+        Self {
+            uid,
+            gid,
+            username: String::from(username.as_ref()),
+            groupname: String::from(groupname.as_ref()),
+        }
+    }
+}
+
+impl Default for FileOwner {
+    fn default() -> Self {
+        Self::new(0, "root", 0, "root")
     }
 }
 
@@ -181,68 +710,568 @@ impl EmptyPackage {
         description: S,
         depends: Vec<String>,
     ) -> Self {
-        Self(Control::new(
-            package,
-            version,
-            architecture,
-            maintainer,
-            description,
-            depends,
-        ))
+        Self::new_from_iter(package, version, architecture, maintainer, description, depends)
+    }
+
+    /// Same as [`EmptyPackage::new`], taking the dependencies from any iterator
+    ///
+    /// e.g. `&["libc6", "libssl3"]`, a `HashSet<String>` or versioned
+    /// [`Dependency`] values.
+    pub fn new_from_iter<S: AsRef<str>, I: IntoIterator<Item = D>, D: Into<Dependency>>(
+        package: S,
+        version: S,
+        architecture: Architecture,
+        maintainer: S,
+        description: S,
+        depends: I,
+    ) -> Self {
+        Self {
+            control: Control::new(
+                package,
+                version,
+                architecture,
+                maintainer,
+                description,
+                depends.into_iter().map(Into::into).collect(),
+            ),
+            files: Vec::new(),
+            symlinks: Vec::new(),
+            conffiles: Vec::new(),
+            preinst: None,
+            postinst: None,
+            prerm: None,
+            postrm: None,
+            compression: CompressionKind::default(),
+            compression_level: Compression::default(),
+            tar_prefix: TarPrefix::default(),
+            timestamp: *TIMESTAMP,
+            copyright: None,
+            include_doc_dir: true,
+        }
+    }
+
+    /// Set the modification time of every archive member
+    ///
+    /// Defaults to `SOURCE_DATE_EPOCH` or the current time with the
+    /// `std-systemtime` feature, and to 0 without it.
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = timestamp;
+    }
+
+    /// Set the compression of the control and data archives, gzip by default
+    pub fn set_compression(&mut self, compression: CompressionKind) {
+        self.compression = compression;
+    }
+
+    /// Set the prefix of the paths inside the data archive, `./` by default
+    pub fn set_tar_prefix(&mut self, tar_prefix: TarPrefix) {
+        self.tar_prefix = tar_prefix;
+    }
+
+    /// Set the compression level of the archives, `Compression::default()` by default
+    ///
+    /// Gzip takes the level as is, xz clamps it to its 0 to 9 presets and
+    /// zstd to its 1 to 19 range.
+    pub fn set_compression_level(&mut self, compression_level: Compression) {
+        self.compression_level = compression_level;
+    }
+
+    pub fn set_pre_depends(&mut self, pre_depends: Vec<String>) {
+        self.control.pre_depends = pre_depends;
+    }
+
+    pub fn set_conflicts(&mut self, conflicts: Vec<String>) {
+        self.control.conflicts = conflicts;
+    }
+
+    pub fn set_provides(&mut self, provides: Vec<String>) {
+        self.control.provides = provides;
+    }
+
+    pub fn set_replaces(&mut self, replaces: Vec<String>) {
+        self.control.replaces = replaces;
+    }
+
+    /// Mark a file added with [`EmptyPackage::add_file`] as a conffile
+    pub fn mark_conffile(&mut self, path: &str) {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        self.conffiles.push(path.to_string());
+    }
+
+    pub fn set_section<S: AsRef<str>>(&mut self, section: S) {
+        self.control.section = Some(String::from(section.as_ref()));
+    }
+
+    pub fn set_priority<S: AsRef<str>>(&mut self, priority: S) {
+        self.control.priority = Some(String::from(priority.as_ref()));
+    }
+
+    pub fn set_homepage<S: AsRef<str>>(&mut self, homepage: S) {
+        self.control.homepage = Some(String::from(homepage.as_ref()));
+    }
+
+    /// Set the source package, e.g. `libfoo (1.0-1)` when the versions differ
+    pub fn set_source<S: AsRef<str>>(&mut self, source: S) {
+        self.control.source = Some(String::from(source.as_ref()));
+    }
+
+    /// Set the sources statically linked in, e.g. `gcc-12 (= 12.2.0-14)`
+    pub fn set_built_using<S: AsRef<str>>(&mut self, built_using: S) {
+        self.control.built_using = Some(String::from(built_using.as_ref()));
+    }
+
+    /// Set the body of the description, one entry per line
+    ///
+    /// Lines are indented by a space, empty lines are rendered as ` .`.
+    pub fn set_long_description(&mut self, long_description: Vec<String>) {
+        self.control.long_description = long_description;
+    }
+
+    pub fn set_preinst(&mut self, script: String) {
+        self.preinst = Some(script);
+    }
+
+    pub fn set_postinst(&mut self, script: String) {
+        self.postinst = Some(script);
+    }
+
+    pub fn set_prerm(&mut self, script: String) {
+        self.prerm = Some(script);
+    }
+
+    pub fn set_postrm(&mut self, script: String) {
+        self.postrm = Some(script);
+    }
+
+    /// Set the contents of `usr/share/doc/<package>/copyright`
+    ///
+    /// A placeholder is shipped when unset, a copyright file added with
+    /// [`EmptyPackage::add_file`] takes precedence.
+    pub fn set_copyright(&mut self, copyright: String) {
+        self.copyright = Some(copyright);
+    }
+
+    /// Ship `usr/share/doc/<package>` and its copyright file, on by default
+    ///
+    /// When off, the copyright set with [`EmptyPackage::set_copyright`] is
+    /// ignored and a package without files has an empty data archive. Files
+    /// added under the doc directory are still shipped.
+    pub fn set_include_doc_dir(&mut self, include_doc_dir: bool) {
+        self.include_doc_dir = include_doc_dir;
+    }
+
+    /// Add a file to the data archive
+    ///
+    /// `path` is relative to the root of the installed system, a leading `/`
+    /// or `./` is ignored.
+    pub fn add_file(&mut self, path: &str, mode: u32, contents: Vec<u8>) {
+        self.add_file_with_owner(path, mode, FileOwner::default(), contents);
+    }
+
+    /// Same as [`EmptyPackage::add_file`], owned by `owner` instead of root
+    pub fn add_file_with_owner(&mut self, path: &str, mode: u32, owner: FileOwner, contents: Vec<u8>) {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        self.files.push(DataFile {
+            path: path.to_string(),
+            mode,
+            owner,
+            md5: format!("{:x}", Md5::digest(&contents)),
+            contents,
+        });
+    }
+
+    /// Add a symbolic link at `link_path` pointing to `target`
+    ///
+    /// `link_path` is handled like the path of [`EmptyPackage::add_file`],
+    /// `target` is stored as is, e.g. `libfoo.so.1` or `/usr/lib/libfoo.so.1`.
+    pub fn add_symlink(&mut self, link_path: &str, target: &str) {
+        let path = link_path.trim_start_matches("./").trim_start_matches('/');
+        self.symlinks.push(DataSymlink {
+            path: path.to_string(),
+            target: target.to_string(),
+        });
+    }
+
+    /// Add every regular file and symbolic link found under `src`
+    ///
+    /// Files are added under `dest_prefix`, e.g. `usr` for `src/bin/foo`
+    /// gives `usr/bin/foo`, with their permission bits on Unix and `0o644`
+    /// elsewhere. Symbolic links are not followed and keep their target.
+    /// Other special files and empty directories are skipped. Entries are
+    /// added in name order so the result does not depend on the filesystem.
+    pub fn add_dir(&mut self, src: &Path, dest_prefix: &str) -> Result<(), Error> {
+        let mut entries = fs::read_dir(src)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name();
+            let name = name
+                .to_str()
+                .ok_or_else(|| anyhow!("Non UTF-8 file name: {:?}", entry.path()))?;
+            let dest = match dest_prefix.trim_end_matches('/') {
+                "" => String::from(name),
+                prefix => format!("{}/{}", prefix, name),
+            };
+            let metadata = entry.path().symlink_metadata()?;
+            if metadata.is_dir() {
+                self.add_dir(&entry.path(), &dest)?;
+            } else if metadata.is_symlink() {
+                let target = fs::read_link(entry.path())?;
+                let target = target
+                    .to_str()
+                    .ok_or_else(|| anyhow!("Non UTF-8 symlink target: {:?}", target))?;
+                self.add_symlink(&dest, target);
+            } else if metadata.is_file() {
+                self.add_file(&dest, file_mode(&metadata), fs::read(entry.path())?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the package name, version and maintainer against the Debian policy
+    pub fn validate(&self) -> Result<(), PackageError> {
+        let control = &self.control;
+        if let Some(field) = control.multiline_field() {
+            return Err(PackageError::MultilineField(field.to_string()));
+        }
+        if !PACKAGE_NAME_REGEX.is_match(&control.package) {
+            return Err(PackageError::InvalidName(control.package.clone()));
+        }
+        if !is_valid_version(&control.version) {
+            return Err(PackageError::InvalidVersion(control.version.clone()));
+        }
+        if !MAINTAINER_REGEX.is_match(&control.maintainer) {
+            return Err(PackageError::InvalidMaintainer(control.maintainer.clone()));
+        }
+        for conffile in self.conffiles.iter() {
+            if !self.files.iter().any(|file| file.path == *conffile) {
+                return Err(PackageError::UnknownConffile(conffile.clone()));
+            }
+        }
+        Ok(())
     }
 
     pub fn build(self) -> Result<Vec<u8>, Error> {
-        let package_name = String::from(self.0.get_name());
-        let control_data = self.0.into_string().into_bytes();
+        let mut ret = Vec::new();
+        self.build_into(&mut ret)?;
+        Ok(ret)
+    }
 
-        // control.tar.gz
-        let mut control_archive_builder = TarBuilder::new(GzEncoder::new(
-            Cursor::new(Vec::new()),
-            Compression::default(),
-        ));
-        let control_header = create_tar_file_header("control", control_data.len());
+    /// Build the package and stream the ar archive into `w`
+    ///
+    /// The control and data archives are still built in memory since ar
+    /// headers need their sizes upfront.
+    ///
+    /// `Installed-Size` is computed from the data files, rounded up to KiB.
+    /// A package without any data, e.g. built with
+    /// [`EmptyPackage::set_include_doc_dir`] off, reports `Installed-Size: 0`
+    /// rather than omitting the field.
+    pub fn build_into<W: Write>(mut self, w: W) -> Result<(), Error> {
+        self.validate()?;
+        let package_name = String::from(self.control.get_name());
+        let copyright_path = format!("{}/{}/copyright", DOC_DIR, package_name);
+        if self.include_doc_dir && !self.files.iter().any(|file| file.path == copyright_path) {
+            let copyright = self.copyright.take().unwrap_or_else(|| DEFAULT_COPYRIGHT.to_string());
+            self.add_file(&copyright_path, 0o644, copyright.into_bytes());
+        }
+        let size: u64 = self.files.iter().map(|file| file.contents.len() as u64).sum();
+        self.control.installed_size = Some(size.div_ceil(1024));
+        let control_data = self.control.into_string()?.into_bytes();
+
+        // control.tar
+        let mut control_archive_builder = TarBuilder::new(Vec::new());
+        let control_header = create_tar_file_header("control", control_data.len(), 0o644, self.timestamp);
         control_archive_builder.append(&control_header, &*control_data)?;
-        let control_archive = control_archive_builder.into_inner()?.finish()?.into_inner();
+        let md5sums: String = self
+            .files
+            .iter()
+            .map(|file| format!("{}  {}\n", file.md5, file.path))
+            .collect();
+        let md5sums_header = create_tar_file_header("md5sums", md5sums.len(), 0o644, self.timestamp);
+        control_archive_builder.append(&md5sums_header, md5sums.as_bytes())?;
+        if !self.conffiles.is_empty() {
+            let conffiles: String = self
+                .conffiles
+                .iter()
+                .map(|path| format!("/{}\n", path))
+                .collect();
+            let conffiles_header =
+                create_tar_file_header("conffiles", conffiles.len(), 0o644, self.timestamp);
+            control_archive_builder.append(&conffiles_header, conffiles.as_bytes())?;
+        }
+        for (name, script) in [
+            ("preinst", &self.preinst),
+            ("postinst", &self.postinst),
+            ("prerm", &self.prerm),
+            ("postrm", &self.postrm),
+        ] {
+            if let Some(script) = script {
+                let script_header = create_tar_file_header(name, script.len(), 0o755, self.timestamp);
+                control_archive_builder.append(&script_header, script.as_bytes())?;
+            }
+        }
+        let control_archive = self.compression.compress(control_archive_builder.into_inner()?, self.compression_level)?;
         let control_archive_size = control_archive.len();
 
-        // data.tar.gz
-        let mut data_archive_builder = TarBuilder::new(GzEncoder::new(
-            Cursor::new(Vec::new()),
-            Compression::default(),
-        ));
-        create_tar_path(
-            format!("{}/{}", DOC_DIR, package_name),
-            &mut data_archive_builder,
-        );
-        let data_archive = data_archive_builder.into_inner()?.finish()?.into_inner();
+        // data.tar
+        let mut data_archive_builder = TarBuilder::new(Vec::new());
+        let mut created_dirs = HashSet::new();
+        if self.include_doc_dir {
+            create_tar_path(
+                format!("{}/{}", DOC_DIR, package_name),
+                self.tar_prefix,
+                self.timestamp,
+                &mut created_dirs,
+                &mut data_archive_builder,
+            );
+        }
+        for file in self.files.iter() {
+            if let Some((parent, _)) = file.path.rsplit_once('/') {
+                create_tar_path(parent, self.tar_prefix, self.timestamp, &mut created_dirs, &mut data_archive_builder);
+            }
+            let path = format!("{}{}", self.tar_prefix, file.path);
+            let mut file_header = create_tar_file_header(
+                &path,
+                file.contents.len(),
+                file.mode,
+                self.timestamp,
+            );
+            file_header.set_uid(file.owner.uid);
+            file_header.set_gid(file.owner.gid);
+            file_header.set_username(&file.owner.username)?;
+            file_header.set_groupname(&file.owner.groupname)?;
+            file_header.set_cksum();
+            append_tar_entry(&mut data_archive_builder, &file_header, &path, &*file.contents)?;
+        }
+        for symlink in self.symlinks.iter() {
+            if let Some((parent, _)) = symlink.path.rsplit_once('/') {
+                create_tar_path(parent, self.tar_prefix, self.timestamp, &mut created_dirs, &mut data_archive_builder);
+            }
+            let path = format!("{}{}", self.tar_prefix, symlink.path);
+            let mut symlink_header = create_tar_file_header(&path, 0, 0o777, self.timestamp);
+            symlink_header.set_entry_type(EntryType::Symlink);
+            if symlink.target.len() > symlink_header.as_old().linkname.len() {
+                append_gnu_long_name(&mut data_archive_builder, EntryType::GNULongLink, &symlink.target)?;
+                copy_truncated(&mut symlink_header.as_old_mut().linkname, &symlink.target);
+            } else {
+                symlink_header.set_link_name(&symlink.target)?;
+            }
+            symlink_header.set_cksum();
+            append_tar_entry(&mut data_archive_builder, &symlink_header, &path, empty())?;
+        }
+        let data_archive = self.compression.compress(data_archive_builder.into_inner()?, self.compression_level)?;
         let data_archive_size = data_archive.len();
 
         // Final package package
-        let mut ret = ArBuilder::new(Cursor::new(Vec::new())); //, AR_IDENTIFIERS.clone());
+        let mut ret = ArBuilder::new(w); //, AR_IDENTIFIERS.clone());
         ret.append(
-            &create_ar_file_header(b"debian-binary".to_vec(), DEBIAN_BINARY.get_ref().len()),
+            &create_ar_file_header(
+                b"debian-binary".to_vec(),
+                DEBIAN_BINARY.get_ref().len(),
+                self.timestamp,
+            ),
             DEBIAN_BINARY.clone(),
         )?;
         ret.append(
-            &create_ar_file_header(b"control.tar.gz".to_vec(), control_archive_size),
+            &create_ar_file_header(
+                format!("control.tar{}", self.compression.extension()).into_bytes(),
+                control_archive_size,
+                self.timestamp,
+            ),
             &*control_archive,
         )?;
         ret.append(
-            &create_ar_file_header(b"data.tar.gz".to_vec(), data_archive_size),
+            &create_ar_file_header(
+                format!("data.tar{}", self.compression.extension()).into_bytes(),
+                data_archive_size,
+                self.timestamp,
+            ),
             &*data_archive,
         )?;
-        Ok(ret.into_inner()?.into_inner())
+        ret.into_inner()?.flush()?;
+        Ok(())
+    }
+}
+
+/// Read the `control` file out of a built package
+pub fn read_control(deb: &[u8]) -> Result<String, Error> {
+    let mut archive = ArArchive::new(deb);
+    while let Some(entry) = archive.next_entry() {
+        let entry = entry?;
+        let name = String::from_utf8_lossy(entry.header().identifier()).to_string();
+        if !name.starts_with("control.tar") {
+            continue;
+        }
+        let compression = CompressionKind::from_member_name(&name)
+            .ok_or_else(|| anyhow!("Unsupported control archive: {}", name))?;
+        let mut control_archive = TarArchive::new(compression.decompress(entry)?);
+        for file in control_archive.entries()? {
+            let mut file = file?;
+            if file.path()?.file_name().is_some_and(|n| n == "control") {
+                let mut ret = String::new();
+                file.read_to_string(&mut ret)?;
+                return Ok(ret);
+            }
+        }
+        return Err(anyhow!("No control file in {}", name));
+    }
+    Err(anyhow!("No control archive in package"))
+}
+
+/// Reader for the ar members of any `.deb` package, not only spiral-built ones
+pub struct DebReader<R: Read + Seek> {
+    archive: ArArchive<R>,
+}
+
+impl<R: Read + Seek> DebReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            archive: ArArchive::new(reader),
+        }
+    }
+
+    /// Name and size of every ar member, in archive order
+    ///
+    /// A corrupt archive lists as empty, [`DebReader::list_files`] reports
+    /// the error instead.
+    pub fn members(&mut self) -> Vec<(String, u64)> {
+        self.try_members().unwrap_or_default()
+    }
+
+    fn try_members(&mut self) -> std::io::Result<Vec<(String, u64)>> {
+        let count = self.archive.count_entries()?;
+        (0..count)
+            .map(|index| {
+                let entry = self.archive.jump_to_entry(index)?;
+                let header = entry.header();
+                Ok((String::from_utf8_lossy(header.identifier()).to_string(), header.size()))
+            })
+            .collect()
+    }
+
+    /// Contents of the ar member called `name`, if there is one
+    pub fn read_member(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.try_read_member(name).ok().flatten()
+    }
+
+    fn try_read_member(&mut self, name: &str) -> std::io::Result<Option<Vec<u8>>> {
+        let count = self.archive.count_entries()?;
+        for index in 0..count {
+            let mut entry = self.archive.jump_to_entry(index)?;
+            if entry.header().identifier() == name.as_bytes() {
+                let mut ret = Vec::new();
+                entry.read_to_end(&mut ret)?;
+                return Ok(Some(ret));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Paths of every entry in the data archive, like `dpkg -c`
+    ///
+    /// Directories are marked with a trailing `/`.
+    pub fn list_files(&mut self) -> Result<Vec<String>, Error> {
+        let name = self
+            .try_members()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .find(|name| name.starts_with("data.tar"))
+            .ok_or_else(|| anyhow!("No data archive in package"))?;
+        let compression = CompressionKind::from_member_name(&name)
+            .ok_or_else(|| anyhow!("Unsupported data archive: {}", name))?;
+        let data = self
+            .try_read_member(&name)?
+            .ok_or_else(|| anyhow!("Failed to read {}", name))?;
+        let mut data_archive = TarArchive::new(compression.decompress(&*data)?);
+        let mut ret = Vec::new();
+        for file in data_archive.entries()? {
+            let file = file?;
+            let mut path = String::from_utf8_lossy(&file.path_bytes()).to_string();
+            if file.header().entry_type() == EntryType::Directory && !path.ends_with('/') {
+                path.push('/');
+            }
+            ret.push(path);
+        }
+        Ok(ret)
     }
 }
 
 #[cfg(test)]
 mod deb_test {
-    use super::{Control, EmptyPackage, Architecture};
+    use super::{Control, ControlParseError, CompressionKind, DebReader, Dependency, EmptyPackage, FileOwner, Relation, TarPrefix, Architecture, PackageError, is_valid_version, read_control};
 
     use anyhow::Error;
+    use ar::Archive as ArArchive;
+    use flate2::read::GzDecoder;
+    use flate2::Compression;
+    use md5::{Digest, Md5};
+    use tar::{Archive as TarArchive, EntryType};
+
+    use std::env;
+    use std::fs::{self, OpenOptions};
+    use std::io::{BufWriter, Cursor, Read, Write};
+
+    /// Extract an ar member from a built package
+    fn read_ar_member(deb: &[u8], name: &str) -> Vec<u8> {
+        let mut archive = ArArchive::new(deb);
+        while let Some(entry) = archive.next_entry() {
+            let mut entry = entry.unwrap();
+            if entry.header().identifier() == name.as_bytes() {
+                let mut ret = Vec::new();
+                entry.read_to_end(&mut ret).unwrap();
+                return ret;
+            }
+        }
+        panic!("Member {} not found", name);
+    }
+
+    /// List the names of all ar members of a built package
+    fn read_ar_members(deb: &[u8]) -> Vec<String> {
+        let mut archive = ArArchive::new(deb);
+        let mut ret = Vec::new();
+        while let Some(entry) = archive.next_entry() {
+            ret.push(String::from_utf8_lossy(entry.unwrap().header().identifier()).to_string());
+        }
+        ret
+    }
+
+    /// List (path, mode, contents) of every entry in a tarball ar member
+    ///
+    /// The decompressor is picked from the member name extension, the same
+    /// way dpkg-deb does.
+    fn read_tar_member(deb: &[u8], name: &str) -> Vec<(String, u32, Vec<u8>)> {
+        let data = read_ar_member(deb, name);
+        let reader: Box<dyn Read> = match name.rsplit_once('.') {
+            Some((_, "gz")) => Box::new(GzDecoder::new(&*data)),
+            Some((_, "xz")) => Box::new(xz2::read::XzDecoder::new(&*data)),
+            Some((_, "zst")) => Box::new(zstd::stream::read::Decoder::new(&*data).unwrap()),
+            Some((_, "tar")) => Box::new(&*data),
+            _ => panic!("Unknown archive member {}", name),
+        };
+        let mut archive = TarArchive::new(reader);
+        archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+                let mode = entry.header().mode().unwrap();
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents).unwrap();
+                (path, mode, contents)
+            })
+            .collect()
+    }
 
-    use std::fs::OpenOptions;
-    use std::io::{BufWriter, Write};
+    fn test_package() -> EmptyPackage {
+        EmptyPackage::new(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file",
+            vec!["test1".to_string(), "test2".to_string()],
+        )
+    }
 
     #[test]
     fn parse_architecture() -> Result<(), Error> {
@@ -271,6 +1300,23 @@ mod deb_test {
         Ok(())
     }
 
+    #[test]
+    fn architecture_gnu_triplet() {
+        for arch in [
+            Architecture::AMD64,
+            Architecture::ARM64,
+            Architecture::PPC64EL,
+            Architecture::RISCV64,
+            Architecture::I486,
+        ] {
+            let triplet = arch.to_gnu_triplet().unwrap();
+            assert_eq!(Architecture::from_gnu_triplet(triplet), Some(arch));
+        }
+        assert_eq!(Architecture::AMD64.to_gnu_triplet(), Some("x86_64-linux-gnu"));
+        assert_eq!(Architecture::ALL.to_gnu_triplet(), None);
+        assert_eq!(Architecture::from_gnu_triplet("x86_64-linux-musl"), None);
+    }
+
     #[test]
     fn create_control_no_dependencies() {
         let control = Control::new(
@@ -282,7 +1328,7 @@ mod deb_test {
             vec![],
         );
         assert_eq!(
-            control.into_string(),
+            control.into_string().unwrap(),
             r#"Package: test
 Version: 0.0.1-0
 Architecture: all
@@ -300,10 +1346,10 @@ Description: Test control file
             Architecture::ALL,
             "Spiral Admin <admin@spiral.v2bv.net>",
             "Test control file",
-            vec!["test1".to_string(), "test2".to_string()],
+            vec!["test1".into(), "test2".into()],
         );
         assert_eq!(
-            control.into_string(),
+            control.into_string().unwrap(),
             r#"Package: test
 Version: 0.0.1-0
 Architecture: all
@@ -315,14 +1361,272 @@ Depends: test1, test2
     }
 
     #[test]
-    fn create_archive() {
-        let package = EmptyPackage::new(
-            "test",
+    fn create_control_with_relationships() {
+        let mut control = Control::new(
+            "libnss3",
             "0.0.1-0",
             Architecture::ALL,
             "Spiral Admin <admin@spiral.v2bv.net>",
             "Test control file",
-            vec!["test1".to_string(), "test2".to_string()],
+            vec!["nss".into()],
+        );
+        control.pre_depends = vec!["dpkg".to_string()];
+        control.conflicts = vec!["libnss3-old".to_string()];
+        control.provides = vec!["libnss3".to_string(), "libnss3-dev".to_string()];
+        control.replaces = vec!["libnss3-old".to_string()];
+        assert_eq!(
+            control.into_string().unwrap(),
+            r#"Package: libnss3
+Version: 0.0.1-0
+Architecture: all
+Maintainer: Spiral Admin <admin@spiral.v2bv.net>
+Description: Test control file
+Pre-Depends: dpkg
+Depends: nss
+Conflicts: libnss3-old
+Provides: libnss3, libnss3-dev
+Replaces: libnss3-old
+"#
+        )
+    }
+
+    #[test]
+    fn create_control_provides_conflicts() {
+        let mut control = Control::new(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file",
+            vec![],
+        );
+        control.conflicts = vec!["test-legacy".to_string()];
+        control.provides = vec!["test-virtual".to_string()];
+        assert_eq!(
+            control.into_string().unwrap(),
+            r#"Package: test
+Version: 0.0.1-0
+Architecture: all
+Maintainer: Spiral Admin <admin@spiral.v2bv.net>
+Description: Test control file
+Conflicts: test-legacy
+Provides: test-virtual
+"#
+        )
+    }
+
+    #[test]
+    fn create_control_multiline_description() {
+        let control = Control::new(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file\nFirst paragraph,\nstill the first one.\n\nSecond paragraph.\n",
+            vec![],
+        );
+        assert_eq!(
+            control.into_string().unwrap(),
+            r#"Package: test
+Version: 0.0.1-0
+Architecture: all
+Maintainer: Spiral Admin <admin@spiral.v2bv.net>
+Description: Test control file
+ First paragraph,
+ still the first one.
+ .
+ Second paragraph.
+"#
+        )
+    }
+
+    #[test]
+    fn parse_control_round_trip() {
+        let mut control = Control::new(
+            "test",
+            "1:0.0.1-0",
+            Architecture::AMD64,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file\nFirst paragraph,\nstill the first one.\n\n  Verbatim line.",
+            vec![
+                Dependency::versioned("libfoo", Relation::GreaterEq, "1.2"),
+                Dependency::any_of(["bar", "baz"]),
+            ],
+        );
+        control.pre_depends = vec!["dpkg (>= 1.15)".to_string()];
+        control.conflicts = vec!["test-old".to_string()];
+        control.provides = vec!["test-virtual".to_string(), "test-alias".to_string()];
+        control.replaces = vec!["test-old".to_string()];
+        control.section = Some("libs".to_string());
+        control.priority = Some("optional".to_string());
+        control.homepage = Some("https://spiral.v2bv.net".to_string());
+        control.installed_size = Some(42);
+        control.source = Some("test-src".to_string());
+        control.built_using = Some("rustc (= 1.70)".to_string());
+        control.extra.insert("Multi-Arch".to_string(), "same".to_string());
+        let text = control.clone().into_string().unwrap();
+        let parsed = Control::parse(&text).unwrap();
+        assert_eq!(parsed, control);
+        assert_eq!(parsed.get_extra_field("multi-arch"), Some("same"));
+        assert_eq!(parsed.into_string().unwrap(), text);
+    }
+
+    #[test]
+    fn parse_control_errors() {
+        let control = "Package: test\nVersion: 1.0\nArchitecture: all\nMaintainer: Spiral Admin <admin@spiral.v2bv.net>\n";
+        assert_eq!(Control::parse(control), Err(ControlParseError::MissingField("Description")));
+        assert_eq!(Control::parse(""), Err(ControlParseError::Empty));
+        let text = format!("{}Description: test\nnot a field\n", control);
+        assert_eq!(Control::parse(&text), Err(ControlParseError::MalformedLine("not a field".to_string())));
+        let text = control.replace("all", "vax") + "Description: test\n";
+        assert_eq!(Control::parse(&text), Err(ControlParseError::InvalidField("Architecture", "vax".to_string())));
+        let control = Control::parse(&read_control(&test_package().build().unwrap()).unwrap()).unwrap();
+        assert_eq!(control.get_name(), "test");
+        assert!(control.installed_size.is_some());
+    }
+
+    #[test]
+    fn create_archive_long_description() {
+        let mut package = test_package();
+        package.set_long_description(vec![
+            "First line of the body.".to_string(),
+            String::new(),
+            "  Verbatim line.".to_string(),
+        ]);
+        let control = read_control(&package.build().unwrap()).unwrap();
+        assert!(control.contains(
+            "\nDescription: Test control file\n First line of the body.\n .\n   Verbatim line.\nDepends: "
+        ));
+
+        let mut package = test_package();
+        package.set_long_description(vec!["Two\nlines".to_string()]);
+        assert_eq!(package.validate(), Err(PackageError::MultilineField("Description".to_string())));
+    }
+
+    #[test]
+    fn create_control_optional_fields() {
+        let mut control = Control::new(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file",
+            vec!["test1".into()],
+        );
+        control.section = Some("libs".to_string());
+        control.priority = Some("optional".to_string());
+        control.homepage = Some("https://github.com/Spiral-repo".to_string());
+        assert_eq!(
+            control.into_string().unwrap(),
+            r#"Package: test
+Version: 0.0.1-0
+Architecture: all
+Maintainer: Spiral Admin <admin@spiral.v2bv.net>
+Section: libs
+Priority: optional
+Homepage: https://github.com/Spiral-repo
+Description: Test control file
+Depends: test1
+"#
+        )
+    }
+
+    #[test]
+    fn create_package_from_iter() {
+        let package = EmptyPackage::new_from_iter(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file",
+            ["test1", "test2"],
+        );
+        assert_eq!(package.control.depends, test_package().control.depends);
+        let control = read_control(&package.build().unwrap()).unwrap();
+        assert!(control.contains("\nDepends: test1, test2\n"));
+    }
+
+    #[test]
+    fn create_package_versioned_dependency() {
+        let package = EmptyPackage::new_from_iter(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file",
+            [Dependency::versioned("libfoo", Relation::GreaterEq, "1.2"), Dependency::from("libbar")],
+        );
+        let control = read_control(&package.build().unwrap()).unwrap();
+        assert!(control.contains("\nDepends: libfoo (>= 1.2), libbar\n"));
+    }
+
+    #[test]
+    fn create_control_alternative_dependency() {
+        let control = Control::new(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file",
+            vec![Dependency::any_of(["a", "b"]), "c".into()],
+        );
+        assert!(control.into_string().unwrap().ends_with("\nDepends: a | b, c\n"));
+    }
+
+    #[test]
+    fn create_control_source_built_using() {
+        let mut package = test_package();
+        package.set_source("test-src (0.0.1-1)");
+        package.set_built_using("gcc-12 (= 12.2.0-14)");
+        assert_eq!(
+            package.control.into_string().unwrap(),
+            r#"Package: test
+Source: test-src (0.0.1-1)
+Version: 0.0.1-0
+Architecture: all
+Maintainer: Spiral Admin <admin@spiral.v2bv.net>
+Description: Test control file
+Depends: test1, test2
+Built-Using: gcc-12 (= 12.2.0-14)
+"#
+        );
+        let mut package = test_package();
+        package.set_source("test\nsrc");
+        assert_eq!(package.validate(), Err(PackageError::MultilineField("Source".to_string())));
+    }
+
+    #[test]
+    fn create_control_some_optional_fields() {
+        let mut control = Control::new(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file",
+            vec![],
+        );
+        control.priority = Some("optional".to_string());
+        assert_eq!(
+            control.into_string().unwrap(),
+            r#"Package: test
+Version: 0.0.1-0
+Architecture: all
+Maintainer: Spiral Admin <admin@spiral.v2bv.net>
+Priority: optional
+Description: Test control file
+"#
+        )
+    }
+
+    #[test]
+    fn create_archive() {
+        let package = EmptyPackage::new(
+            "test",
+            "0.0.1-0",
+            Architecture::ALL,
+            "Spiral Admin <admin@spiral.v2bv.net>",
+            "Test control file",
+            vec!["test1".into(), "test2".into()],
         );
         let f = OpenOptions::new()
             .write(true)
@@ -333,4 +1637,505 @@ Depends: test1, test2
         let mut f = BufWriter::new(f);
         f.write_all(&package.build().unwrap()).unwrap();
     }
+
+    #[test]
+    fn create_archive_with_files() {
+        let mut package = test_package();
+        package.add_file("/usr/bin/hello", 0o755, b"#!/bin/sh\necho hello\n".to_vec());
+        package.add_file("etc/hello.conf", 0o644, b"greeting=hello\n".to_vec());
+        let deb = package.build().unwrap();
+        let data = read_tar_member(&deb, "data.tar.gz");
+
+        let (_, mode, contents) = data.iter().find(|(path, _, _)| path == "./usr/bin/hello").unwrap();
+        assert_eq!(*mode, 0o755);
+        assert_eq!(contents, b"#!/bin/sh\necho hello\n");
+        let (_, mode, contents) = data.iter().find(|(path, _, _)| path == "./etc/hello.conf").unwrap();
+        assert_eq!(*mode, 0o644);
+        assert_eq!(contents, b"greeting=hello\n");
+        assert!(data.iter().any(|(path, _, _)| path == "./usr/share/doc/test/"));
+    }
+
+    #[test]
+    fn create_archive_tar_prefix() {
+        for (prefix, expected) in [(TarPrefix::Dot, "./"), (TarPrefix::None, ""), (TarPrefix::Slash, "/")] {
+            let mut package = test_package();
+            package.set_tar_prefix(prefix);
+            package.add_file("usr/bin/hello", 0o755, b"hello".to_vec());
+            let deb = package.build().unwrap();
+            let data = read_tar_member(&deb, "data.tar.gz");
+            assert!(data.iter().any(|(path, _, _)| *path == format!("{}usr/bin/hello", expected)));
+            assert!(data.iter().any(|(path, _, _)| *path == format!("{}usr/bin/", expected)));
+            for (path, _, _) in data {
+                assert!(path.starts_with(expected), "{} does not start with {:?}", path, expected);
+                assert!(!path.is_empty() && !path.starts_with("//") && !path.starts_with(".//"), "{}", path);
+            }
+        }
+    }
+
+    #[test]
+    fn create_archive_root_once() {
+        let mut package = test_package();
+        package.add_file("usr/bin/hello", 0o755, b"hello".to_vec());
+        package.add_file("etc/hello.conf", 0o644, b"greeting=hello\n".to_vec());
+        let deb = package.build().unwrap();
+        let data = read_tar_member(&deb, "data.tar.gz");
+        assert_eq!(data.iter().filter(|(path, _, _)| path == "./").count(), 1);
+        for (path, _, _) in data {
+            assert!(path.starts_with("./") && !path.contains("//"), "{}", path);
+        }
+    }
+
+    #[test]
+    fn create_archive_directories_once() {
+        let mut package = test_package();
+        package.add_file("usr/bin/hello", 0o755, b"hello".to_vec());
+        package.add_file("usr/bin/world", 0o755, b"world".to_vec());
+        package.add_file("usr/share/hello/hello.txt", 0o644, b"hello\n".to_vec());
+        let deb = package.build().unwrap();
+        let dirs: Vec<String> = read_tar_member(&deb, "data.tar.gz")
+            .into_iter()
+            .map(|(path, _, _)| path)
+            .filter(|path| path.ends_with('/'))
+            .collect();
+        assert_eq!(dirs, [
+            "./",
+            "./usr/",
+            "./usr/share/",
+            "./usr/share/doc/",
+            "./usr/share/doc/test/",
+            "./usr/bin/",
+            "./usr/share/hello/",
+        ]);
+    }
+
+    #[test]
+    fn create_archive_without_doc_dir() {
+        let mut package = test_package();
+        package.set_include_doc_dir(false);
+        package.set_copyright("Copyright: 2023 Spiral Admin\n".to_string());
+        let deb = package.build().unwrap();
+        assert!(read_tar_member(&deb, "data.tar.gz").is_empty());
+        let control = read_tar_member(&deb, "control.tar.gz");
+        let (_, _, md5sums) = control.iter().find(|(path, _, _)| path == "md5sums").unwrap();
+        assert!(md5sums.is_empty());
+
+        let mut package = test_package();
+        package.set_include_doc_dir(false);
+        package.add_file("usr/bin/hello", 0o755, b"hello".to_vec());
+        let deb = package.build().unwrap();
+        let data = read_tar_member(&deb, "data.tar.gz");
+        assert!(data.iter().any(|(path, _, _)| path == "./usr/bin/hello"));
+        assert!(!data.iter().any(|(path, _, _)| path.starts_with("./usr/share/doc")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_archive_from_dir() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let src = env::temp_dir().join(format!("spiral-add-dir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&src);
+        fs::create_dir_all(src.join("bin")).unwrap();
+        fs::create_dir_all(src.join("lib/empty")).unwrap();
+        fs::write(src.join("bin/hello"), b"#!/bin/sh\necho hello\n").unwrap();
+        fs::set_permissions(src.join("bin/hello"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(src.join("lib/libfoo.so.1"), b"foo").unwrap();
+        fs::set_permissions(src.join("lib/libfoo.so.1"), fs::Permissions::from_mode(0o644)).unwrap();
+        symlink("libfoo.so.1", src.join("lib/libfoo.so")).unwrap();
+
+        let mut package = test_package();
+        package.add_dir(&src, "/usr/").unwrap();
+        fs::remove_dir_all(&src).unwrap();
+        let deb = package.build().unwrap();
+        let data = read_tar_member(&deb, "data.tar.gz");
+        let entries: Vec<(&str, u32)> = data
+            .iter()
+            .filter(|(path, _, _)| !path.ends_with('/'))
+            .map(|(path, mode, _)| (path.as_str(), *mode))
+            .collect();
+        assert_eq!(entries, [
+            ("./usr/bin/hello", 0o755),
+            ("./usr/lib/libfoo.so.1", 0o644),
+            ("./usr/share/doc/test/copyright", 0o644),
+            ("./usr/lib/libfoo.so", 0o777),
+        ]);
+        assert!(!data.iter().any(|(path, _, _)| path.contains("empty")));
+        let (_, _, contents) = data.iter().find(|(path, _, _)| path == "./usr/bin/hello").unwrap();
+        assert_eq!(contents, b"#!/bin/sh\necho hello\n");
+        let files = DebReader::new(Cursor::new(deb)).list_files().unwrap();
+        assert!(files.contains(&"./usr/lib/libfoo.so".to_string()));
+    }
+
+    #[test]
+    fn create_archive_symlink() {
+        let mut package = test_package();
+        package.add_file("usr/lib/libfoo.so.1", 0o644, b"foo".to_vec());
+        package.add_symlink("/usr/lib/libfoo.so", "libfoo.so.1");
+        let deb = package.build().unwrap();
+        let data = read_ar_member(&deb, "data.tar.gz");
+        let mut archive = TarArchive::new(GzDecoder::new(&*data));
+        let symlink = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .find(|entry| &*entry.path_bytes() == b"./usr/lib/libfoo.so")
+            .unwrap();
+        assert_eq!(symlink.header().entry_type(), EntryType::Symlink);
+        assert_eq!(symlink.link_name().unwrap().unwrap().to_str(), Some("libfoo.so.1"));
+        let control = read_tar_member(&deb, "control.tar.gz");
+        let (_, _, md5sums) = control.iter().find(|(path, _, _)| path == "md5sums").unwrap();
+        assert!(!String::from_utf8_lossy(md5sums).contains("libfoo.so\n"));
+    }
+
+    #[test]
+    fn create_archive_long_path() {
+        let dir = format!("usr/share/{}", ["deeply/nested/directory"; 6].join("/"));
+        let file = format!("{}/{}.txt", dir, "f".repeat(120));
+        let target = format!("/{}/target", dir);
+        assert!(file.len() > 100 && dir.len() > 100 && target.len() > 100);
+        let mut package = test_package();
+        package.add_file(&file, 0o644, b"long".to_vec());
+        package.add_symlink(&format!("{}/link", dir), &target);
+        let deb = package.build().unwrap();
+        let data = read_tar_member(&deb, "data.tar.gz");
+        let (_, _, contents) = data.iter().find(|(path, _, _)| *path == format!("./{}", file)).unwrap();
+        assert_eq!(contents, b"long");
+        assert!(data.iter().any(|(path, _, _)| *path == format!("./{}/", dir)));
+        let data = read_ar_member(&deb, "data.tar.gz");
+        let mut archive = TarArchive::new(GzDecoder::new(&*data));
+        let symlink = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .find(|entry| entry.header().entry_type() == EntryType::Symlink)
+            .unwrap();
+        assert_eq!(&*symlink.path_bytes(), format!("./{}/link", dir).as_bytes());
+        assert_eq!(&*symlink.link_name_bytes().unwrap(), target.as_bytes());
+    }
+
+    #[test]
+    fn create_archive_file_owner() {
+        let mut package = test_package();
+        package.add_file_with_owner("usr/bin/hello", 0o755, FileOwner::new(1000, "spiral", 100, "users"), b"hello".to_vec());
+        package.add_file("etc/hello.conf", 0o644, b"greeting=hello\n".to_vec());
+        let deb = package.build().unwrap();
+        let data = read_ar_member(&deb, "data.tar.gz");
+        let mut archive = TarArchive::new(GzDecoder::new(&*data));
+        let headers: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().header().clone())
+            .collect();
+        let find = |path: &[u8]| headers.iter().find(|header| &*header.path_bytes() == path).unwrap();
+        let hello = find(b"./usr/bin/hello");
+        assert_eq!(hello.mode().unwrap(), 0o755);
+        assert_eq!((hello.uid().unwrap(), hello.gid().unwrap()), (1000, 100));
+        assert_eq!((hello.username().unwrap(), hello.groupname().unwrap()), (Some("spiral"), Some("users")));
+        let conf = find(b"./etc/hello.conf");
+        assert_eq!((conf.uid().unwrap(), conf.gid().unwrap()), (0, 0));
+        assert_eq!((conf.username().unwrap(), conf.groupname().unwrap()), (Some("root"), Some("root")));
+    }
+
+    #[test]
+    fn create_archive_md5sums() {
+        let mut package = test_package();
+        package.add_file("usr/share/hello/hello.txt", 0o644, b"hello\n".to_vec());
+        package.add_file("./usr/share/hello/empty", 0o644, Vec::new());
+        let deb = package.build().unwrap();
+        let control = read_tar_member(&deb, "control.tar.gz");
+        let (_, mode, md5sums) = control.iter().find(|(path, _, _)| path == "md5sums").unwrap();
+        assert_eq!(*mode, 0o644);
+        assert!(String::from_utf8_lossy(md5sums).starts_with(
+            "b1946ac92492d2347c6235b4d2611184  usr/share/hello/hello.txt\n\
+             d41d8cd98f00b204e9800998ecf8427e  usr/share/hello/empty\n"
+        ));
+    }
+
+    #[test]
+    fn create_archive_default_md5sums() {
+        let deb = test_package().build().unwrap();
+        let control = read_tar_member(&deb, "control.tar.gz");
+        let (_, _, md5sums) = control.iter().find(|(path, _, _)| path == "md5sums").unwrap();
+        let md5sums = String::from_utf8_lossy(md5sums);
+        assert_eq!(md5sums.lines().count(), 1);
+        assert!(md5sums.ends_with("  usr/share/doc/test/copyright\n"));
+    }
+
+    #[test]
+    fn create_archive_maintainer_scripts() {
+        let mut package = test_package();
+        package.set_postinst("#!/bin/sh\nldconfig\n".to_string());
+        package.set_prerm("#!/bin/sh\nexit 0\n".to_string());
+        let deb = package.build().unwrap();
+        let control = read_tar_member(&deb, "control.tar.gz");
+
+        let (_, mode, script) = control.iter().find(|(path, _, _)| path == "postinst").unwrap();
+        assert_eq!(*mode, 0o755);
+        assert_eq!(script, b"#!/bin/sh\nldconfig\n");
+        let (_, mode, script) = control.iter().find(|(path, _, _)| path == "prerm").unwrap();
+        assert_eq!(*mode, 0o755);
+        assert_eq!(script, b"#!/bin/sh\nexit 0\n");
+        assert!(control.iter().all(|(path, _, _)| path != "preinst" && path != "postrm"));
+    }
+
+    #[test]
+    fn create_archive_compression() {
+        for (compression, extension) in [
+            (CompressionKind::Gzip, ".gz"),
+            (CompressionKind::Xz, ".xz"),
+            (CompressionKind::Zstd, ".zst"),
+            (CompressionKind::None, ""),
+        ] {
+            let mut package = test_package();
+            package.set_compression(compression);
+            package.add_file("usr/bin/hello", 0o755, b"hello".to_vec());
+            let deb = package.build().unwrap();
+            let control_name = format!("control.tar{}", extension);
+            let data_name = format!("data.tar{}", extension);
+            assert_eq!(read_ar_members(&deb), vec!["debian-binary", &control_name, &data_name]);
+
+            let control = read_tar_member(&deb, &control_name);
+            assert!(control.iter().any(|(path, _, _)| path == "control"));
+            let data = read_tar_member(&deb, &data_name);
+            let (_, _, contents) = data.iter().find(|(path, _, _)| path == "./usr/bin/hello").unwrap();
+            assert_eq!(contents, b"hello");
+        }
+    }
+
+    #[test]
+    fn create_archive_compression_level() {
+        let payload: String = (0..20000).map(|i| format!("{} {}\n", i, i * i % 97)).collect();
+        let build = |kind: CompressionKind, level: Compression| {
+            let mut package = test_package();
+            package.set_compression(kind);
+            package.set_compression_level(level);
+            package.add_file("usr/share/test/payload", 0o644, payload.clone().into_bytes());
+            package.build().unwrap()
+        };
+        let fast = build(CompressionKind::Gzip, Compression::fast());
+        let best = build(CompressionKind::Gzip, Compression::best());
+        assert!(best.len() < fast.len());
+        assert_eq!(read_tar_member(&fast, "data.tar.gz"), read_tar_member(&best, "data.tar.gz"));
+        let fast = build(CompressionKind::Zstd, Compression::fast());
+        let best = build(CompressionKind::Zstd, Compression::new(19));
+        assert!(best.len() < fast.len());
+        assert_eq!(read_tar_member(&fast, "data.tar.zst"), read_tar_member(&best, "data.tar.zst"));
+        let fast = build(CompressionKind::Xz, Compression::fast());
+        let best = build(CompressionKind::Xz, Compression::new(19));
+        assert_eq!(best, build(CompressionKind::Xz, Compression::best()));
+        assert_eq!(read_tar_member(&fast, "data.tar.xz"), read_tar_member(&best, "data.tar.xz"));
+    }
+
+    #[test]
+    fn validate_package_name() {
+        for name in ["test", "libiso9660++0", "g++-12", "0ad", "libc6.1"] {
+            let package = EmptyPackage::new(name, "1.0", Architecture::ALL, "Spiral Admin <admin@spiral.v2bv.net>", "Test", vec![]);
+            assert_eq!(package.validate(), Ok(()));
+        }
+        for name in ["Test", "test package", "t", "-test", ""] {
+            let package = EmptyPackage::new(name, "1.0", Architecture::ALL, "Spiral Admin <admin@spiral.v2bv.net>", "Test", vec![]);
+            assert_eq!(package.validate(), Err(PackageError::InvalidName(name.to_string())));
+            assert!(package.build().is_err());
+        }
+    }
+
+    #[test]
+    fn validate_package_version() {
+        for version in ["0.0.1-0", "1:2.3~rc1-1+b1", "2.0", "1.2-3-4", "1:2:3", "20230221"] {
+            assert!(is_valid_version(version), "{}", version);
+        }
+        for version in ["", "a1.0", "1.0-", ":1.0", "a:1.0", "1.0 beta", "1.0_1", "1.0-r_1"] {
+            assert!(!is_valid_version(version), "{}", version);
+        }
+        let package = EmptyPackage::new("test", "v1", Architecture::ALL, "Spiral Admin <admin@spiral.v2bv.net>", "Test", vec![]);
+        assert_eq!(package.validate(), Err(PackageError::InvalidVersion("v1".to_string())));
+    }
+
+    #[test]
+    fn validate_multiline_field() {
+        let mut package = test_package();
+        package.set_section("libs\nPriority: required");
+        assert_eq!(package.validate(), Err(PackageError::MultilineField("Section".to_string())));
+
+        let mut package = test_package();
+        package.set_provides(vec!["test3\nEssential: yes".to_string()]);
+        assert_eq!(package.validate(), Err(PackageError::MultilineField("Provides".to_string())));
+    }
+
+    #[test]
+    fn validate_package_maintainer() {
+        for maintainer in ["Spiral Admin", "<admin@spiral.v2bv.net>", "Spiral Admin admin@spiral.v2bv.net"] {
+            let package = EmptyPackage::new("test", "1.0", Architecture::ALL, maintainer, "Test", vec![]);
+            assert_eq!(package.validate(), Err(PackageError::InvalidMaintainer(maintainer.to_string())));
+        }
+    }
+
+    #[test]
+    fn read_control_round_trip() {
+        for compression in [CompressionKind::Gzip, CompressionKind::Xz, CompressionKind::Zstd, CompressionKind::None] {
+            let mut package = test_package();
+            package.set_compression(compression);
+            let deb = package.build().unwrap();
+            assert_eq!(
+                read_control(&deb).unwrap(),
+                r#"Package: test
+Version: 0.0.1-0
+Architecture: all
+Maintainer: Spiral Admin <admin@spiral.v2bv.net>
+Installed-Size: 1
+Description: Test control file
+Depends: test1, test2
+"#
+            );
+        }
+        assert!(read_control(b"!<arch>\n").is_err());
+    }
+
+    #[test]
+    fn deb_reader_members() {
+        let deb = test_package().build().unwrap();
+        let mut reader = DebReader::new(Cursor::new(deb));
+        let members = reader.members();
+        let names: Vec<&str> = members.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["debian-binary", "control.tar.gz", "data.tar.gz"]);
+        assert!(members.iter().all(|(_, size)| *size > 0));
+        assert_eq!(reader.read_member("debian-binary"), Some(b"2.0\n".to_vec()));
+        assert_eq!(reader.read_member("data.tar.xz"), None);
+    }
+
+    #[test]
+    fn deb_reader_list_files() {
+        for compression in [CompressionKind::Gzip, CompressionKind::Xz, CompressionKind::Zstd] {
+            let mut package = test_package();
+            package.set_compression(compression);
+            package.add_file("usr/bin/hello", 0o755, b"hello".to_vec());
+            package.add_file("etc/hello.conf", 0o644, b"greeting=hello\n".to_vec());
+            let mut reader = DebReader::new(Cursor::new(package.build().unwrap()));
+            let files = reader.list_files().unwrap();
+            for path in ["./", "./usr/", "./usr/bin/", "./usr/bin/hello", "./etc/", "./etc/hello.conf"] {
+                assert!(files.iter().any(|file| file == path), "{} missing from {:?}", path, files);
+            }
+            assert!(!files.iter().any(|file| file == "./usr/bin/hello/"));
+        }
+        assert!(DebReader::new(Cursor::new(b"!<arch>\n".to_vec())).list_files().is_err());
+
+        let mut corrupt = b"!<arch>\n".to_vec();
+        corrupt.extend_from_slice(&[b'x'; 60]);
+        let mut reader = DebReader::new(Cursor::new(corrupt));
+        assert!(reader.members().is_empty());
+        let err = reader.list_files().unwrap_err();
+        assert!(err.downcast_ref::<std::io::Error>().is_some(), "{}", err);
+    }
+
+    #[test]
+    fn architecture_iter() {
+        assert_eq!(Architecture::all().count(), 14);
+        assert_eq!(Architecture::real_architectures().count(), 13);
+        assert!(Architecture::all().any(|arch| arch == Architecture::ALL));
+        assert!(!Architecture::real_architectures().any(|arch| arch == Architecture::ALL));
+        for name in ["amd64", "riscv64"] {
+            assert!(Architecture::real_architectures().any(|arch| arch.to_string() == name));
+        }
+    }
+
+    #[test]
+    fn compression_from_member_name() {
+        assert_eq!(CompressionKind::from_member_name("data.tar.gz"), Some(CompressionKind::Gzip));
+        assert_eq!(CompressionKind::from_member_name("control.tar.xz"), Some(CompressionKind::Xz));
+        assert_eq!(CompressionKind::from_member_name("data.tar.zst"), Some(CompressionKind::Zstd));
+        assert_eq!(CompressionKind::from_member_name("data.tar"), Some(CompressionKind::None));
+        assert_eq!(CompressionKind::from_member_name("data.tar.bz2"), None);
+    }
+
+    #[test]
+    fn create_archive_reproducible() {
+        let build = || {
+            let mut package = test_package();
+            package.set_timestamp(1676937600);
+            package.add_file("usr/bin/hello", 0o755, b"hello".to_vec());
+            package.build().unwrap()
+        };
+        let deb = build();
+        assert_eq!(deb, build());
+
+        let mut archive = ArArchive::new(&*deb);
+        while let Some(entry) = archive.next_entry() {
+            assert_eq!(entry.unwrap().header().mtime(), 1676937600);
+        }
+        let data = read_ar_member(&deb, "data.tar.gz");
+        let mut archive = TarArchive::new(GzDecoder::new(&*data));
+        for entry in archive.entries().unwrap() {
+            assert_eq!(entry.unwrap().header().mtime().unwrap(), 1676937600);
+        }
+    }
+
+    #[test]
+    fn create_archive_conffiles() {
+        let mut package = test_package();
+        package.add_file("/etc/test/test.conf", 0o644, b"key=value\n".to_vec());
+        package.mark_conffile("/etc/test/test.conf");
+        let deb = package.build().unwrap();
+        let control = read_tar_member(&deb, "control.tar.gz");
+        let (_, _, conffiles) = control.iter().find(|(path, _, _)| path == "conffiles").unwrap();
+        assert_eq!(conffiles, b"/etc/test/test.conf\n");
+
+        let mut package = test_package();
+        package.mark_conffile("/etc/test/missing.conf");
+        assert_eq!(package.validate(), Err(PackageError::UnknownConffile("etc/test/missing.conf".to_string())));
+        assert!(package.build().is_err());
+    }
+
+    #[test]
+    fn create_archive_build_into() {
+        let package = || {
+            let mut package = test_package();
+            package.set_timestamp(1676937600);
+            package.add_file("usr/bin/hello", 0o755, b"hello".to_vec());
+            package
+        };
+        let mut deb = Vec::new();
+        package().build_into(&mut deb).unwrap();
+        assert_eq!(deb, package().build().unwrap());
+    }
+
+    #[test]
+    fn create_archive_installed_size() {
+        let mut package = test_package();
+        package.add_file("usr/share/doc/test/copyright", 0o644, Vec::new());
+        package.add_file("usr/share/test/a", 0o644, vec![0; 1024]);
+        package.add_file("usr/share/test/b", 0o644, vec![0; 1025]);
+        let control = read_control(&package.build().unwrap()).unwrap();
+        assert!(control.contains("\nInstalled-Size: 3\n"));
+
+        let control = read_control(&test_package().build().unwrap()).unwrap();
+        assert!(control.contains("\nInstalled-Size: 1\n"));
+
+        let mut package = test_package();
+        package.set_include_doc_dir(false);
+        let control = read_control(&package.build().unwrap()).unwrap();
+        assert!(control.contains("\nInstalled-Size: 0\n"));
+    }
+
+    #[test]
+    fn create_archive_copyright() {
+        let deb = test_package().build().unwrap();
+        let data = read_tar_member(&deb, "data.tar.gz");
+        let (_, mode, copyright) = data.iter().find(|(path, _, _)| path == "./usr/share/doc/test/copyright").unwrap();
+        assert_eq!(*mode, 0o644);
+        assert!(!copyright.is_empty());
+
+        let mut package = test_package();
+        package.set_copyright("Copyright: 2023 Spiral Admin\n".to_string());
+        let deb = package.build().unwrap();
+        let data = read_tar_member(&deb, "data.tar.gz");
+        let copyrights: Vec<_> = data.iter().filter(|(path, _, _)| path == "./usr/share/doc/test/copyright").collect();
+        assert_eq!(copyrights.len(), 1);
+        assert_eq!(copyrights[0].2, b"Copyright: 2023 Spiral Admin\n");
+        let control = read_tar_member(&deb, "control.tar.gz");
+        let (_, _, md5sums) = control.iter().find(|(path, _, _)| path == "md5sums").unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(md5sums),
+            format!("{:x}  usr/share/doc/test/copyright\n", Md5::digest(b"Copyright: 2023 Spiral Admin\n"))
+        );
+    }
 }
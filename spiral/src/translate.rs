@@ -1,15 +1,33 @@
 use serde::{Serialize, Deserialize};
 use apt_parser::Filter;
+use apt_parser::contents::SharedLibrary;
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+
+use std::collections::HashSet;
+
+lazy_static! {
+    /// Captures the soname and the sover of a shared library inside a
+    /// Contents line
+    static ref SONAME_REGEX: Regex = Regex::new(r"(?:^|/)([^/\s]+)\.so((?:\.[0-9]+)*)\s").unwrap();
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Lib {
     library_name: String,
     sover: Vec<usize>,
+    package_name: Option<String>,
+    #[serde(default = "default_sover_components")]
+    sover_components: usize,
+}
+
+fn default_sover_components() -> usize {
+    1
 }
 
 #[derive(Clone, Debug)]
 pub struct ContentsFilter {
-    name: Vec<(String, String)>,
+    names: HashSet<String>,
 }
 
 impl Lib {
@@ -20,18 +38,57 @@ impl Lib {
         Self {
             library_name: library_name.as_ref().replace('_', "-").to_lowercase(),
             sover,
+            package_name: None,
+            sover_components: default_sover_components(),
         }
     }
 
+    /// Set how many sover components end up in the translated name, 1 by default
+    ///
+    /// Libraries with a shorter sover use every component they have.
+    pub fn with_sover_components(mut self, sover_components: usize) -> Self {
+        self.sover_components = sover_components;
+        self
+    }
+
+    /// Set the package shipping the library
+    pub fn with_package_name<S: AsRef<str>>(mut self, package_name: S) -> Self {
+        self.package_name = Some(package_name.as_ref().to_string());
+        self
+    }
+
     pub fn get_lib_name(&self) -> &str {
         &self.library_name
     }
 
+    /// Name of the package shipping the library, defaults to the library name
+    pub fn get_package_name(&self) -> &str {
+        self.package_name.as_deref().unwrap_or(&self.library_name)
+    }
+
+    /// Whether the library name already encodes a version, e.g. `libfoo-1`
+    fn has_versioned_name(&self) -> bool {
+        self.library_name
+            .rsplit_once('-')
+            .is_some_and(|(_, suffix)| !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    /// Translated package name of the library
+    ///
+    /// The sover components, joined by `.`, are appended unless the name
+    /// already ends with a `-<version>` suffix.
     pub fn get_translated_lib_name(&self) -> String {
-        let version_suffix = if self.sover.is_empty() {
+        let version_suffix = if self.sover.is_empty() || self.sover_components == 0 || self.has_versioned_name() {
             None
         } else {
-            Some(self.sover[0])
+            Some(
+                self.sover
+                    .iter()
+                    .take(self.sover_components)
+                    .map(|component| component.to_string())
+                    .collect::<Vec<_>>()
+                    .join("."),
+            )
         };
         let end_numeric = self.library_name.chars().last().unwrap().is_numeric();
         let lib_name = self.get_lib_name();
@@ -50,26 +107,92 @@ impl Lib {
     pub fn get_sover(&self) -> &[usize] {
         &self.sover
     }
+
+    /// Whether `regex` matches the translated, dev or base name of the library
+    pub fn matches(&self, regex: &regex::Regex) -> bool {
+        regex.is_match(&self.get_translated_lib_name())
+            || regex.is_match(&self.get_translated_dev_name())
+            || regex.is_match(self.get_lib_name())
+    }
+
+    /// Whether any regex of `set` matches the translated, dev or base name
+    /// of the library
+    pub fn matches_any(&self, set: &regex::RegexSet) -> bool {
+        set.is_match(&self.get_translated_lib_name())
+            || set.is_match(&self.get_translated_dev_name())
+            || set.is_match(self.get_lib_name())
+    }
 }
 
-impl ContentsFilter {
-    fn new<S: AsRef<str>>(names: Vec<S>) -> Self {
-        unimplemented!()
+/// Reuse the sover parsed out of a Contents file
+impl From<&SharedLibrary> for Lib {
+    fn from(so: &SharedLibrary) -> Self {
+        Self::new(so.get_name(), so.get_sover().to_vec())
     }
+}
 
-    fn extract_libname<S: AsRef<str>>(name: S) -> String {
-        let mut s = name.as_ref().trim();
-        if s.ends_with("-dev") {
-            s = &s[..s.len() - 4];
+impl ContentsFilter {
+    pub fn new<S: AsRef<str>>(names: Vec<S>) -> Self {
+        Self {
+            names: names
+                .into_iter()
+                .map(|name| name.as_ref().trim().replace('_', "-").to_lowercase())
+                .collect(),
         }
-        
-        "".to_string()
+    }
+}
+
+/// Accept lines whose library translates to one of the requested names
+///
+/// A library matches through either its translated name, e.g.
+/// `libiso9660-11` for `libiso9660.so.11.0.0`, or its dev name, e.g.
+/// `libiso9660-dev` for any `libiso9660.so*`.
+impl Filter for ContentsFilter {
+    fn filter_bytes(&self, input: &[u8]) -> bool {
+        let Some(captures) = SONAME_REGEX.captures(input) else {
+            return false;
+        };
+        let sover: Option<Vec<usize>> = captures[2]
+            .split(|chr| *chr == b'.')
+            .skip(1)
+            .map(|component| std::str::from_utf8(component).ok()?.parse().ok())
+            .collect();
+        let Some(sover) = sover else {
+            return false;
+        };
+        let lib = Lib::from(&SharedLibrary::from_bytes(&captures[1], sover));
+        self.names.contains(&lib.get_translated_lib_name()) || self.names.contains(&lib.get_translated_dev_name())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Lib;
+    use super::{ContentsFilter, Lib};
+
+    use apt_parser::{AcceptAllFilter, Filter};
+    use apt_parser::contents::{ContentsEntry, ContentsIterator, ContentsSharedLibraryIterator, File, SharedLibrary};
+
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn lib_matches_translated_name() {
+        let lib = Lib::new("libiso9660", vec![11, 0, 0]);
+        assert!(lib.matches(&regex::Regex::new("^libiso9660-11$").unwrap()));
+        assert!(!lib.matches(&regex::Regex::new("^libiso9660-12$").unwrap()));
+    }
+
+    #[test]
+    fn lib_matches_dev_name() {
+        let lib = Lib::new("libiso9660", vec![11, 0, 0]);
+        assert!(lib.matches(&regex::Regex::new("^libiso9660-dev$").unwrap()));
+    }
+
+    #[test]
+    fn lib_matches_lib_name() {
+        let lib = Lib::new("libiso9660", vec![11, 0, 0]);
+        assert!(lib.matches(&regex::Regex::new("^libiso9660$").unwrap()));
+    }
 
     #[test]
     fn lib_get_lib_name_libadwaitaqt1() {
@@ -95,6 +218,59 @@ mod test {
         assert_eq!("libiso9660-11", lib.get_translated_lib_name());
     }
 
+    #[test]
+    fn lib_get_lib_name_versioned_name() {
+        assert_eq!("libfoo-1", Lib::new("libfoo-1", vec![2]).get_translated_lib_name());
+        assert_eq!("libfoo-1", Lib::new("libfoo_1", vec![2, 0]).get_translated_lib_name());
+        assert_eq!("libbar2", Lib::new("libbar", vec![2]).get_translated_lib_name());
+        assert_eq!("libfoo-bar2", Lib::new("libfoo-bar", vec![2]).get_translated_lib_name());
+    }
+
+    #[test]
+    fn lib_get_lib_name_sover_components() {
+        let lib = Lib::new("libfoo", vec![2, 3, 4]);
+        assert_eq!("libfoo2", lib.clone().with_sover_components(1).get_translated_lib_name());
+        assert_eq!("libfoo2.3", lib.clone().with_sover_components(2).get_translated_lib_name());
+        assert_eq!("libfoo2.3.4", lib.clone().with_sover_components(5).get_translated_lib_name());
+        assert_eq!("libfoo", lib.with_sover_components(0).get_translated_lib_name());
+        assert_eq!("libiso9660-11.0", Lib::new("libiso9660", vec![11, 0, 0]).with_sover_components(2).get_translated_lib_name());
+    }
+
+    #[test]
+    fn lib_from_shared_library() {
+        let entry = ContentsEntry::parse("./usr/lib/libiso9660.so.11.0.0   libs/libcdio").unwrap();
+        let File::SharedLibrary(so) = entry.get_path().get_file() else {
+            panic!("Expected a shared library");
+        };
+        let lib = Lib::from(so);
+        assert_eq!(lib.get_sover(), &[11, 0, 0]);
+        assert_eq!(lib.get_translated_lib_name(), Lib::new("libiso9660", vec![11, 0, 0]).get_translated_lib_name());
+        assert_eq!("libiso9660-11", lib.get_translated_lib_name());
+        assert_eq!("libiso9660-dev", lib.get_translated_dev_name());
+    }
+
+    #[test]
+    fn lib_from_shared_library_sover() {
+        let so = SharedLibrary::from_bytes(b"libnuma", vec![1, 1, 4]);
+        let lib = Lib::from(&so).with_package_name("numactl");
+        assert_eq!(lib.get_sover(), so.get_sover());
+        assert_eq!(lib.get_package_name(), "numactl");
+    }
+
+    #[test]
+    fn lib_from_shared_library_dummy() {
+        let path = format!("{}/../apt_parser/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap());
+        let names: Vec<String> = ContentsSharedLibraryIterator::new(fs::File::open(path).unwrap(), AcceptAllFilter::new())
+            .filter_map(|entry| match entry.get_path().get_file() {
+                File::SharedLibrary(so) => Some(Lib::from(so).get_translated_lib_name()),
+                File::Normal(_) => None,
+            })
+            .collect();
+        assert_eq!(names.len(), 18);
+        assert!(names.iter().any(|name| name == "libiso9660-11"));
+        assert!(names.iter().any(|name| name == "libadwaitaqt1"));
+    }
+
     #[test]
     fn lib_get_dev_name_libadwaitaqt1() {
         let lib = Lib::new("libadwaitaqt", vec![1, 4, 0]);
@@ -118,4 +294,51 @@ mod test {
         let lib = Lib::new("libiso9660", vec![11, 0, 0]);
         assert_eq!("libiso9660-dev", lib.get_translated_dev_name());
     }
+
+    #[test]
+    fn contents_filter_filter_bytes() {
+        let filter = ContentsFilter::new(vec!["libnss3-dev"]);
+        assert!(filter.filter_bytes(b"./usr/lib/libnss3.so   libs/nss\n"));
+        assert!(!filter.filter_bytes(b"usr/lib/libnotify.so.4   libs/libnotify\n"));
+        assert!(!filter.filter_bytes(b"usr/bin/bash   shells/bash\n"));
+        let filter = ContentsFilter::new(vec!["libadwaitaqt1", "libiso9660++0", "libXtst-dev"]);
+        assert!(filter.filter_bytes(b"usr/lib/libadwaitaqt.so.1.4.0   gnome/adwaita-qt\n"));
+        assert!(!filter.filter_bytes(b"usr/lib/libadwaitaqt.so.2   gnome/adwaita-qt\n"));
+        assert!(filter.filter_bytes(b"usr/lib/libiso9660++.so.0   libs/libcdio\n"));
+        assert!(!filter.filter_bytes(b"usr/lib/libiso9660.so.0   libs/libcdio\n"));
+        assert!(filter.filter_bytes(b"usr/lib/libXtst.so.6   x11/x11-lib\n"));
+    }
+
+    #[test]
+    fn contents_filter_digits_in_name() {
+        let filter = ContentsFilter::new(vec!["libx11-dev"]);
+        assert!(filter.filter_bytes(b"usr/lib/x86_64-linux-gnu/libX11.so.6   libs/libx11-6\n"));
+        assert!(!filter.filter_bytes(b"usr/lib/x86_64-linux-gnu/libx264.so.164   libs/libx264-164\n"));
+        assert!(!filter.filter_bytes(b"usr/lib/x86_64-linux-gnu/libx.so.11   libs/libx\n"));
+        let filter = ContentsFilter::new(vec!["libx264-164"]);
+        assert!(filter.filter_bytes(b"usr/lib/x86_64-linux-gnu/libx264.so.164   libs/libx264-164\n"));
+        assert!(!filter.filter_bytes(b"usr/lib/x86_64-linux-gnu/libx26.so.4   libs/libx26\n"));
+    }
+
+    #[test]
+    fn contents_filter_multiarch() {
+        let filter = ContentsFilter::new(vec!["libnss3", "libiso9660-11"]);
+        assert!(filter.filter_bytes(b"usr/lib/x86_64-linux-gnu/libnss3.so   libs/nss\n"));
+        assert!(filter.filter_bytes(b"./usr/lib/aarch64-linux-gnu/libiso9660.so.11.0.0   libs/libcdio\n"));
+        assert!(!filter.filter_bytes(b"usr/lib/x86_64-linux-gnu/libnotify.so.4   libs/libnotify\n"));
+    }
+
+    #[test]
+    fn contents_filter_dummy() {
+        let path = format!("{}/../apt_parser/tests/Contents-amd64-dummy", env::var("CARGO_MANIFEST_DIR").unwrap());
+        for (names, count) in [
+            (vec!["libiso9660-11"], 2),
+            (vec!["libiso9660-dev"], 3),
+            (vec!["libiso9660++0"], 2),
+            (vec!["libadwaitaqt1", "libxtst-dev"], 5),
+        ] {
+            let parser = ContentsIterator::new(fs::File::open(&path).unwrap(), ContentsFilter::new(names));
+            assert_eq!(parser.count(), count);
+        }
+    }
 }